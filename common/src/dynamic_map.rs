@@ -185,15 +185,15 @@ impl<T: CellDisplay> DynamicMap<T> {
         self.show_with_path(f, &Vec::new())
     }
 
-    pub fn show_with_msg(&self, f: &mut dyn Write, msg: &String) -> Result<()> {
+    pub fn show_with_msg(&self, f: &mut dyn Write, msg: &str) -> Result<()> {
         self.show_with_path_msg(f, &Vec::new(), msg)
     }
 
     pub fn show_with_path(&self, f: &mut dyn Write, path: &[PointU]) -> Result<()> {
-        self.show_with_path_msg(f, path, &"".to_string())
+        self.show_with_path_msg(f, path, "")
     }
 
-    pub fn show_with_path_msg(&self, f: &mut dyn Write, path: &[PointU], msg: &String) -> Result<()> {
+    pub fn show_with_path_msg(&self, f: &mut dyn Write, path: &[PointU], msg: &str) -> Result<()> {
         let mut buf = String::new();
         let abs_pos = self.abs_position();
 
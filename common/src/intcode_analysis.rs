@@ -0,0 +1,700 @@
+use crate::intcode_comp::*;
+use crate::intcode_disasm::*;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Number of instructions before a jump searched for the pushed return address
+const CALL_LOOKBACK: usize = 4;
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+pub enum EdgeKind {
+    FallThrough,
+    Jump,
+    /// Jump to the function entry
+    Call,
+    /// From the call site to the instruction the function returns to
+    CallReturn,
+}
+
+/// How the block passes control besides its static successors
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+pub enum BlockExit {
+    /// Only to the successors
+    Static,
+    Halt,
+    /// Jump to the address read using relative mode
+    Return,
+    /// Jump to the address read from memory
+    Indirect,
+    /// Continues into cells that can not be decoded
+    Invalid,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Block {
+    pub start: usize,
+    /// Address after the last instruction
+    pub end: usize,
+    /// Addresses of the block instructions
+    pub instructions: Vec<usize>,
+    pub exit: BlockExit,
+    pub successors: Vec<(usize, EdgeKind)>,
+}
+
+/// Code called using the relative base stack convention: return address is written
+/// using relative mode right before the jump, function returns through relative mode jump.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Function {
+    pub entry: usize,
+    /// Stack frame allocated by `ARB` at the entry
+    pub frame_size: Option<DataType>,
+    /// Blocks reachable from the entry without following calls
+    pub blocks: Vec<usize>,
+    /// Addresses of jumps calling the function
+    pub call_sites: Vec<usize>,
+    /// Entries of the functions called from this one
+    pub calls: Vec<usize>,
+}
+
+/// Instruction writing into a cell of another decoded instruction
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+pub struct SelfModification {
+    pub ip: usize,
+    pub addr: usize,
+    /// Address of the modified instruction
+    pub target_ip: usize,
+}
+
+/// Cells `start..end`
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+pub struct CodeRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Summary {
+    pub size: usize,
+    pub code_cells: usize,
+    pub data_cells: usize,
+    pub entry_points: Vec<usize>,
+    pub blocks: usize,
+    pub edges: usize,
+    pub functions: Vec<Function>,
+    pub self_modifications: Vec<SelfModification>,
+    pub unreachable: Vec<CodeRange>,
+}
+
+/// Result of the static analysis of the program image
+#[derive(Debug)]
+pub struct Analysis {
+    listing: Listing,
+    entry_points: BTreeSet<usize>,
+    blocks: BTreeMap<usize, Block>,
+    functions: BTreeMap<usize, Function>,
+    self_modifications: Vec<SelfModification>,
+    unreachable: Vec<CodeRange>,
+}
+
+impl Analysis {
+    pub fn listing(&self) -> &Listing {
+        &self.listing
+    }
+
+    /// Entry points including addresses the functions return to
+    pub fn entry_points(&self) -> &BTreeSet<usize> {
+        &self.entry_points
+    }
+
+    pub fn blocks(&self) -> &BTreeMap<usize, Block> {
+        &self.blocks
+    }
+
+    pub fn functions(&self) -> &BTreeMap<usize, Function> {
+        &self.functions
+    }
+
+    pub fn self_modifications(&self) -> &[SelfModification] {
+        &self.self_modifications
+    }
+
+    /// Statically unreachable cells decodable into instructions ending with a jump or halt
+    pub fn unreachable(&self) -> &[CodeRange] {
+        &self.unreachable
+    }
+
+    pub fn summary(&self) -> Summary {
+        let code_cells = self
+            .listing
+            .entries()
+            .iter()
+            .filter(|e| e.is_code())
+            .map(|e| e.size())
+            .sum();
+        let size = self.listing.entries().iter().map(|e| e.size()).sum();
+
+        Summary {
+            size,
+            code_cells,
+            data_cells: size - code_cells,
+            entry_points: self.entry_points.iter().cloned().collect(),
+            blocks: self.blocks.len(),
+            edges: self.blocks.values().map(|b| b.successors.len()).sum(),
+            functions: self.functions.values().cloned().collect(),
+            self_modifications: self.self_modifications.clone(),
+            unreachable: self.unreachable.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.summary())?)
+    }
+
+    /// Graphviz control-flow graph. Function entries have double border,
+    /// blocks modified by the program are red.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let modified: BTreeSet<usize> = self
+            .self_modifications
+            .iter()
+            .map(|m| m.target_ip)
+            .collect();
+
+        let _ = writeln!(out, "digraph intcode {{");
+        let _ = writeln!(out, "    node [shape=box, fontname=\"monospace\"];");
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for addr in &block.instructions {
+                if let Some(entry) = self.listing.get(*addr) {
+                    let _ = write!(label, "{}\\l", entry);
+                }
+            }
+
+            let mut attrs = format!("label=\"{}\"", label);
+            if self.functions.contains_key(&block.start) {
+                attrs += ", peripheries=2";
+            }
+            if block.instructions.iter().any(|ip| modified.contains(ip)) {
+                attrs += ", color=red";
+            }
+            let _ = writeln!(out, "    b{} [{}];", block.start, attrs);
+
+            for (to, kind) in &block.successors {
+                let style = match kind {
+                    EdgeKind::FallThrough => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [label=\"call\", style=bold]",
+                    EdgeKind::CallReturn => " [label=\"return\", style=dashed]",
+                };
+                let _ = writeln!(out, "    b{} -> b{}{};", block.start, to, style);
+            }
+        }
+
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
+
+/// Static analyzer. Decodes program with the standard instruction set starting from
+/// address 0 (and any extra entry points), follows jumps with immediate targets
+/// and continues after detected calls.
+pub struct Analyzer<'p> {
+    prog: &'p [DataType],
+    entry_points: BTreeSet<usize>,
+}
+
+impl<'p> Analyzer<'p> {
+    pub fn new(prog: &'p [DataType]) -> Self {
+        let mut entry_points = BTreeSet::new();
+        entry_points.insert(0);
+
+        Self { prog, entry_points }
+    }
+
+    pub fn add_entry_point(&mut self, addr: usize) {
+        self.entry_points.insert(addr);
+    }
+
+    pub fn analyze(&self) -> Analysis {
+        let mut entry_points = self.entry_points.clone();
+        // Jump address -> function entry
+        let mut calls = BTreeMap::new();
+
+        // Code after the call is found only when the call itself is decoded
+        let listing = loop {
+            let mut disasm = Disassembler::new(self.prog);
+            entry_points
+                .iter()
+                .for_each(|&addr| disasm.add_entry_point(addr));
+            let listing = disasm.disassemble();
+
+            let mut found = false;
+            for entry in listing.entries() {
+                if let Some((target, ret)) = call_target(&listing, entry) {
+                    calls.insert(entry.addr(), target);
+                    found |= entry_points.insert(ret);
+                }
+            }
+
+            if !found {
+                break listing;
+            }
+        };
+
+        let blocks = build_blocks(&listing, &entry_points, &calls);
+        let functions = find_functions(&listing, &blocks, &calls);
+        let self_modifications = find_self_modifications(&listing);
+        let unreachable = find_unreachable(&listing);
+
+        Analysis {
+            listing,
+            entry_points,
+            blocks,
+            functions,
+            self_modifications,
+            unreachable,
+        }
+    }
+}
+
+pub fn analyze(prog: &[DataType]) -> Analysis {
+    Analyzer::new(prog).analyze()
+}
+
+/// `Some(true)` if the jump is always taken, `Some(false)` if never, `None` if it depends on data
+fn jump_taken(cmd: Command, cond: Operand) -> Option<bool> {
+    match (cmd, cond) {
+        (Command::JumpIfTrue(..), Operand::Immediate(cond)) => Some(cond != 0),
+        (Command::JumpIfFalse(..), Operand::Immediate(cond)) => Some(cond == 0),
+        _ => None,
+    }
+}
+
+/// Jump condition and target operands
+fn jump_operands(entry: &Entry) -> Option<(Command, Operand, Operand)> {
+    match entry {
+        Entry::Code { cmd, operands, .. } => match (cmd, operands.as_slice()) {
+            (Command::JumpIfTrue(..), [cond, target])
+            | (Command::JumpIfFalse(..), [cond, target]) => Some((*cmd, *cond, *target)),
+            _ => None,
+        },
+        Entry::Data { .. } => None,
+    }
+}
+
+fn ends_block(entry: &Entry) -> bool {
+    match entry {
+        Entry::Code { cmd, .. } => matches!(
+            cmd,
+            Command::Exit | Command::JumpIfTrue(..) | Command::JumpIfFalse(..)
+        ),
+        Entry::Data { .. } => true,
+    }
+}
+
+/// Function entry and return address if the entry is an unconditional jump
+/// preceded by writing the address of the following instruction using relative mode
+fn call_target(listing: &Listing, entry: &Entry) -> Option<(usize, usize)> {
+    let (cmd, cond, target) = jump_operands(entry)?;
+    let target = match target {
+        Operand::Immediate(target) if target >= 0 => target as usize,
+        _ => return None,
+    };
+    if jump_taken(cmd, cond) != Some(true) {
+        return None;
+    }
+
+    let ret = entry.addr() + entry.size();
+    let mut addr = entry.addr();
+
+    for _ in 0..CALL_LOOKBACK {
+        let prev = listing.get(addr.checked_sub(1)?)?;
+        if ends_block(prev) {
+            return None;
+        }
+
+        if let Entry::Code { cmd, operands, .. } = prev {
+            let value = match (cmd, operands.as_slice()) {
+                (
+                    Command::Add(..),
+                    [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)],
+                ) => a + b,
+                (
+                    Command::Mul(..),
+                    [Operand::Immediate(a), Operand::Immediate(b), Operand::Relative(_)],
+                ) => a * b,
+                _ => -1,
+            };
+            if value == ret as DataType {
+                return Some((target, ret));
+            }
+        }
+
+        addr = prev.addr();
+    }
+
+    None
+}
+
+fn build_blocks(
+    listing: &Listing,
+    entry_points: &BTreeSet<usize>,
+    calls: &BTreeMap<usize, usize>,
+) -> BTreeMap<usize, Block> {
+    let code: Vec<&Entry> = listing.entries().iter().filter(|e| e.is_code()).collect();
+    let is_code_start = |addr: usize| {
+        listing
+            .get(addr)
+            .is_some_and(|e| e.is_code() && e.addr() == addr)
+    };
+
+    let mut leaders: BTreeSet<usize> = entry_points.clone();
+    leaders.extend(calls.values());
+    for entry in &code {
+        if let Entry::Code {
+            addr, jump_target, ..
+        } = entry
+        {
+            if *jump_target {
+                leaders.insert(*addr);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<&Entry>> = Vec::new();
+    let mut open = false;
+    for entry in code {
+        let continues = open
+            && !leaders.contains(&entry.addr())
+            && groups
+                .last()
+                .and_then(|g| g.last())
+                .is_some_and(|last| last.addr() + last.size() == entry.addr());
+
+        if continues {
+            groups.last_mut().unwrap().push(entry);
+        } else {
+            groups.push(vec![entry]);
+        }
+        open = !ends_block(entry);
+    }
+
+    let mut blocks = BTreeMap::new();
+    for group in groups {
+        let first = group[0];
+        let last = group[group.len() - 1];
+        let end = last.addr() + last.size();
+
+        let mut exit = BlockExit::Static;
+        let mut successors = Vec::new();
+        let mut fall_through = true;
+
+        if let Entry::Code {
+            cmd: Command::Exit, ..
+        } = last
+        {
+            exit = BlockExit::Halt;
+            fall_through = false;
+        }
+
+        if let Some((cmd, cond, target)) = jump_operands(last) {
+            let taken = jump_taken(cmd, cond);
+            fall_through = taken != Some(true);
+
+            if taken != Some(false) {
+                match target {
+                    Operand::Immediate(target) if target >= 0 && is_code_start(target as usize) => {
+                        let target = target as usize;
+                        if calls.contains_key(&last.addr()) {
+                            successors.push((target, EdgeKind::Call));
+                            successors.push((end, EdgeKind::CallReturn));
+                        } else {
+                            successors.push((target, EdgeKind::Jump));
+                        }
+                    }
+                    Operand::Immediate(_) => exit = BlockExit::Invalid,
+                    Operand::Relative(_) => exit = BlockExit::Return,
+                    _ => exit = BlockExit::Indirect,
+                }
+            }
+        }
+
+        if fall_through {
+            if is_code_start(end) {
+                successors.push((end, EdgeKind::FallThrough));
+            } else {
+                exit = BlockExit::Invalid;
+            }
+        }
+
+        blocks.insert(
+            first.addr(),
+            Block {
+                start: first.addr(),
+                end,
+                instructions: group.iter().map(|e| e.addr()).collect(),
+                exit,
+                successors,
+            },
+        );
+    }
+
+    blocks
+}
+
+fn find_functions(
+    listing: &Listing,
+    blocks: &BTreeMap<usize, Block>,
+    calls: &BTreeMap<usize, usize>,
+) -> BTreeMap<usize, Function> {
+    let mut functions = BTreeMap::new();
+
+    for &entry in calls.values() {
+        if functions.contains_key(&entry) || !blocks.contains_key(&entry) {
+            continue;
+        }
+
+        let mut reached = BTreeSet::new();
+        let mut queue = vec![entry];
+        while let Some(start) = queue.pop() {
+            if !reached.insert(start) {
+                continue;
+            }
+            if let Some(block) = blocks.get(&start) {
+                queue.extend(
+                    block
+                        .successors
+                        .iter()
+                        .filter(|(_, kind)| *kind != EdgeKind::Call)
+                        .map(|(to, _)| *to),
+                );
+            }
+        }
+
+        let frame_size = match listing.get(entry) {
+            Some(Entry::Code {
+                cmd: Command::AdjustRelBase(_),
+                operands,
+                ..
+            }) => match operands.as_slice() {
+                [Operand::Immediate(size)] => Some(*size),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let called: BTreeSet<usize> = reached
+            .iter()
+            .filter_map(|start| blocks[start].instructions.last())
+            .filter_map(|ip| calls.get(ip).cloned())
+            .collect();
+
+        functions.insert(
+            entry,
+            Function {
+                entry,
+                frame_size,
+                blocks: reached.into_iter().collect(),
+                call_sites: calls
+                    .iter()
+                    .filter(|(_, target)| **target == entry)
+                    .map(|(ip, _)| *ip)
+                    .collect(),
+                calls: called.into_iter().collect(),
+            },
+        );
+    }
+
+    functions
+}
+
+fn find_self_modifications(listing: &Listing) -> Vec<SelfModification> {
+    let mut result = Vec::new();
+
+    for entry in listing.entries() {
+        let (ip, cmd, operands) = match entry {
+            Entry::Code {
+                addr,
+                cmd,
+                operands,
+                ..
+            } => (*addr, cmd, operands),
+            Entry::Data { .. } => continue,
+        };
+
+        let dest = match cmd {
+            Command::Add(..) | Command::Mul(..) | Command::LessThan(..) | Command::Equals(..) => {
+                operands[2]
+            }
+            Command::Read(..) => operands[0],
+            _ => continue,
+        };
+
+        if let Operand::Position(addr) = dest {
+            if addr < 0 {
+                continue;
+            }
+            if let Some(target) = listing.get(addr as usize).filter(|e| e.is_code()) {
+                result.push(SelfModification {
+                    ip,
+                    addr: addr as usize,
+                    target_ip: target.addr(),
+                });
+            }
+        }
+    }
+
+    result
+}
+
+fn find_unreachable(listing: &Listing) -> Vec<CodeRange> {
+    let mut result = Vec::new();
+    let mut run: Vec<DataType> = Vec::new();
+    let mut start = 0;
+
+    let entries = listing.entries();
+    for (idx, entry) in entries.iter().enumerate() {
+        if let Entry::Data { addr, value } = entry {
+            if run.is_empty() {
+                start = *addr;
+            }
+            run.push(*value);
+        }
+
+        let run_ends = entry.is_code() || idx + 1 == entries.len();
+        if run_ends && !run.is_empty() {
+            if let Some(end) = decode_run(&run) {
+                result.push(CodeRange {
+                    start,
+                    end: start + end,
+                });
+            }
+            run.clear();
+        }
+    }
+
+    result
+}
+
+/// Length of the instruction sequence starting at the beginning of the cells
+/// if it ends with a jump or halt
+fn decode_run(cells: &[DataType]) -> Option<usize> {
+    let mut addr = 0;
+
+    while addr < cells.len() {
+        let (cmd, params_count) = Command::parse(addr, cells[addr]).ok()?;
+        addr += params_count + 1;
+        if addr > cells.len() {
+            return None;
+        }
+
+        if matches!(
+            cmd,
+            Command::Exit | Command::JumpIfTrue(..) | Command::JumpIfFalse(..)
+        ) {
+            return Some(addr);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+
+    const PROG: &str = "
+              ARB  #stack
+              GET  [n]
+              CALL square
+              SET  [n]
+              JIF  [n], #end
+              ADD  #99, #0, [patch]
+        patch:
+              ADD  #0, #0, [n]
+        end:  EXIT
+              ADD  [n], [n], [n]
+              EXIT
+      square: ARB  #2
+              MUL  [n], [n], [n]
+              ARB  #-2
+              RET
+        n:    db 0
+        stack:
+    ";
+
+    #[test]
+    fn test_analysis() -> Result<()> {
+        let prog = assemble(PROG)?;
+        let analysis = analyze(&prog);
+
+        // ARB(2) + GET(2) + ADD(4) + ARB(2) + JIT(3)
+        let ret = 13;
+        let call = ret - 3;
+        let patch = ret + 2 + 3 + 4;
+        let end = patch + 4;
+        let square = end + 1 + 4 + 1;
+        // ARB(2) + MUL(4) + ARB(2) + RET(2 + 3)
+        let n = square + 13;
+
+        assert!(analysis.entry_points().contains(&ret));
+        assert_eq!(
+            analysis.functions().values().collect::<Vec<_>>(),
+            vec![&Function {
+                entry: square,
+                frame_size: Some(2),
+                blocks: vec![square],
+                call_sites: vec![call],
+                calls: vec![],
+            }]
+        );
+
+        let blocks = analysis.blocks();
+        assert_eq!(
+            blocks[&0].successors,
+            vec![(square, EdgeKind::Call), (ret, EdgeKind::CallReturn)]
+        );
+        assert_eq!(
+            blocks[&ret].successors,
+            vec![(end, EdgeKind::Jump), (ret + 5, EdgeKind::FallThrough)]
+        );
+        assert_eq!(blocks[&end].exit, BlockExit::Halt);
+        assert_eq!(blocks[&square].exit, BlockExit::Return);
+
+        assert_eq!(
+            analysis.self_modifications(),
+            &[SelfModification {
+                ip: patch - 4,
+                addr: patch,
+                target_ip: patch
+            }]
+        );
+        assert_eq!(
+            analysis.unreachable(),
+            &[CodeRange {
+                start: end + 1,
+                end: square
+            }]
+        );
+
+        let summary = analysis.summary();
+        assert_eq!(summary.size, prog.len());
+        assert_eq!(summary.blocks, 5);
+        assert_eq!(summary.edges, 5);
+        assert!(analysis.to_json()?.contains("\"frame_size\": 2"));
+
+        let dot = analysis.to_dot();
+        assert!(dot.contains(&format!("b{} [label=\"[{:4}]>ARB  i[2]", square, square)));
+        assert!(dot.contains(", peripheries=2"));
+        assert!(dot.contains(&format!("b0 -> b{} [label=\"call\", style=bold];", square)));
+        assert!(dot.contains(&format!(
+            "[{:4}] ADD  i[0], i[0], p[{}]\\l\", color=red",
+            patch, n
+        )));
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+use crate::intcode_comp::*;
+use crate::log::*;
+use anyhow::Result;
+use std::io::{self, BufRead, Write};
+
+/// Output of the ASCII program. Values outside of ASCII range (like computed answers)
+/// are collected separately from the text.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct AsciiOutput {
+    pub text: String,
+    pub values: Vec<DataType>,
+}
+
+impl AsciiOutput {
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.lines()
+    }
+
+    /// Last non-ASCII value
+    pub fn value(&self) -> Option<DataType> {
+        self.values.last().cloned()
+    }
+}
+
+pub fn is_ascii(value: DataType) -> bool {
+    (0..128).contains(&value)
+}
+
+/// Text followed by a newline
+pub fn encode_line(line: &str) -> Vec<DataType> {
+    line.bytes()
+        .map(|b| b as DataType)
+        .chain(std::iter::once(10))
+        .collect()
+}
+
+pub fn decode(data: &[DataType]) -> AsciiOutput {
+    let mut result = AsciiOutput::default();
+
+    for &value in data {
+        if is_ascii(value) {
+            result.text.push(value as u8 as char);
+        } else {
+            result.values.push(value);
+        }
+    }
+
+    result
+}
+
+/// Line based wrapper of the `IntcodeComp` running ASCII programs
+pub struct AsciiComp<'l> {
+    comp: IntcodeComp<'l>,
+    /// Output that was not read yet
+    pending: AsciiOutput,
+}
+
+impl<'l> AsciiComp<'l> {
+    pub fn new(comp: IntcodeComp<'l>) -> Self {
+        Self {
+            comp,
+            pending: AsciiOutput::default(),
+        }
+    }
+
+    pub fn from_prog(prog: &str, log: &'l Log) -> Result<Self> {
+        let mut comp = IntcodeComp::new(Vec::new(), log);
+        comp.load_prog(prog)?;
+        Ok(Self::new(comp))
+    }
+
+    pub fn comp(&self) -> &IntcodeComp<'l> {
+        &self.comp
+    }
+
+    pub fn comp_mut(&mut self) -> &mut IntcodeComp<'l> {
+        &mut self.comp
+    }
+
+    pub fn into_comp(self) -> IntcodeComp<'l> {
+        self.comp
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.comp.add_input_vec(&mut encode_line(line));
+    }
+
+    pub fn send_lines(&mut self, lines: &[&str]) {
+        for line in lines {
+            self.send_line(line);
+        }
+    }
+
+    /// Run until program waits for input or halts and return all unread output
+    pub fn run(&mut self) -> Result<AsciiOutput> {
+        self.fill()?;
+        Ok(std::mem::take(&mut self.pending))
+    }
+
+    /// Run whole program and return all unread output
+    pub fn exec(&mut self) -> Result<AsciiOutput> {
+        self.comp.exec()?;
+        self.take_output();
+        Ok(std::mem::take(&mut self.pending))
+    }
+
+    /// Text up to and including `prompt`. If program stops before printing the prompt
+    /// all remaining output is returned.
+    pub fn read_until(&mut self, prompt: &str) -> Result<AsciiOutput> {
+        loop {
+            if let Some(pos) = self.pending.text.find(prompt) {
+                let rest = self.pending.text.split_off(pos + prompt.len());
+                let text = std::mem::replace(&mut self.pending.text, rest);
+
+                return Ok(AsciiOutput {
+                    text,
+                    values: std::mem::take(&mut self.pending.values),
+                });
+            }
+
+            if !self.fill()? {
+                return Ok(std::mem::take(&mut self.pending));
+            }
+        }
+    }
+
+    /// Next line without newline. None if program stopped and there is no text left.
+    pub fn read_line(&mut self) -> Result<Option<String>> {
+        let mut output = self.read_until("\n")?;
+
+        // Keep values until they are taken explicitly
+        self.pending.values.append(&mut output.values);
+
+        if output.text.is_empty() {
+            return Ok(None);
+        }
+        if output.text.ends_with('\n') {
+            output.text.pop();
+        }
+
+        Ok(Some(output.text))
+    }
+
+    /// Non-ASCII values that were not read yet
+    pub fn take_values(&mut self) -> Vec<DataType> {
+        std::mem::take(&mut self.pending.values)
+    }
+
+    /// Pass program text to stdout and lines typed in stdin to the program
+    pub fn interactive(&mut self) -> Result<()> {
+        let stdin = io::stdin();
+        self.interactive_with(&mut stdin.lock(), &mut io::stdout())
+    }
+
+    /// Non-ASCII values are written as numbers on separate lines.
+    /// Stops when program halts, is interrupted or reader is exhausted.
+    pub fn interactive_with(
+        &mut self,
+        reader: &mut dyn BufRead,
+        writer: &mut dyn Write,
+    ) -> Result<()> {
+        loop {
+            let output = self.run()?;
+
+            write!(writer, "{}", output.text)?;
+            for value in output.values {
+                writeln!(writer, "{}", value)?;
+            }
+            writer.flush()?;
+
+            if self.comp.is_halted() || self.comp.is_interrupted() {
+                return Ok(());
+            }
+
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+
+            self.send_line(line.trim_end_matches(&['\r', '\n'][..]));
+        }
+    }
+
+    /// Run the program if it can make progress. Returns false otherwise or if the program
+    /// was interrupted.
+    fn fill(&mut self) -> Result<bool> {
+        if self.comp.is_halted()
+            || (self.comp.get_status() == Status::WaitForInput && self.comp.input().is_empty())
+        {
+            return Ok(false);
+        }
+
+        self.comp.run()?;
+        self.take_output();
+
+        Ok(!self.comp.is_interrupted())
+    }
+
+    fn take_output(&mut self) {
+        let output = decode(&self.comp.get_output());
+
+        self.pending.text += &output.text;
+        self.pending.values.extend(output.values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+
+    // Asks for a name, greets and prints length of the name
+    const PROG: &str = "
+              SET  #78      ; Name?
+              SET  #97
+              SET  #109
+              SET  #101
+              SET  #63
+              SET  #10
+        read: GET  [ch]
+              EQ   [ch], #10, [done]
+              JIT  [done], #greet
+              ADD  [len], #1, [len]
+              JIT  #1, #read
+       greet: SET  #72      ; Hi
+              SET  #105
+              SET  #10
+              MUL  [len], #1000, [len]
+              SET  [len]
+              EXIT
+        ch:   db 0
+        done: db 0
+        len:  db 0
+    ";
+
+    fn ascii_comp(log: &Log) -> Result<AsciiComp<'_>> {
+        let prog = assemble(PROG)?;
+        Ok(AsciiComp::new(IntcodeComp::new(prog, log)))
+    }
+
+    #[test]
+    fn test_read() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = ascii_comp(&log)?;
+
+        assert_eq!(comp.read_until("?")?.text, "Name?");
+        assert_eq!(comp.read_line()?, Some("".to_string()));
+        assert_eq!(comp.read_line()?, None);
+
+        comp.send_line("Bob");
+        assert_eq!(comp.read_line()?, Some("Hi".to_string()));
+        assert_eq!(comp.read_line()?, None);
+        assert_eq!(comp.take_values(), vec![3000]);
+        assert!(comp.comp().is_halted());
+        Ok(())
+    }
+
+    #[test]
+    fn test_exec() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = ascii_comp(&log)?;
+
+        comp.send_lines(&["Alice"]);
+        let output = comp.exec()?;
+        assert_eq!(output.lines().collect::<Vec<_>>(), vec!["Name?", "Hi"]);
+        assert_eq!(output.value(), Some(5000));
+        Ok(())
+    }
+
+    #[test]
+    fn test_interactive() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = ascii_comp(&log)?;
+        let mut writer = Vec::new();
+
+        comp.interactive_with(&mut "Eve\r\nignored\n".as_bytes(), &mut writer)?;
+        assert_eq!(String::from_utf8(writer)?, "Name?\nHi\n3000\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,843 @@
+//! Intcode assembler.
+//!
+//! Syntax (one statement per line, `;` starts a comment):
+//!
+//! ```text
+//! SIZE  equ 3               ; named constant
+//! start:                    ; label
+//!       GET  [value]        ; position mode
+//!       ADD  [value], #SIZE, rb+1
+//!       SET  rb+1           ; relative mode, rb-N is allowed too
+//!       CALL func           ; macros: PUSH, POP, CALL, RET
+//!       EXIT
+//! func: RET
+//! value: db 0, -1, "text\n" ; data
+//!       ds 10               ; 10 zero cells
+//! ```
+//!
+//! Macros use relative base as a stack pointer growing upwards:
+//! `PUSH x` stores `x` at `rb+0` and increments relative base, `POP x` does the opposite,
+//! `CALL f` pushes return address and jumps to `f`, `RET` pops it and jumps back.
+
+use crate::intcode_comp::*;
+use crate::intcode_memory::DEFAULT_MAX_ADDR;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new<S: Into<String>>(line: usize, column: usize, message: S) -> Self {
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+type AsmResult<T> = std::result::Result<T, AsmError>;
+
+/// Assemble source text into the program image
+pub fn assemble(src: &str) -> Result<Vec<DataType>> {
+    Ok(Assembler::new().assemble(src)?)
+}
+
+/// Comma separated representation accepted by `IntcodeComp::load_prog`
+pub fn to_prog_str(prog: &[DataType]) -> String {
+    prog.iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum TokenKind {
+    Ident(String),
+    Number(DataType),
+    Str(Vec<DataType>),
+    Comma,
+    Colon,
+    Hash,
+    LBracket,
+    RBracket,
+    Plus,
+    Minus,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn tokenize(line_no: usize, line: &str) -> AsmResult<Vec<Token>> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let single = match c {
+            ',' => Some(TokenKind::Comma),
+            ':' => Some(TokenKind::Colon),
+            '#' => Some(TokenKind::Hash),
+            '[' => Some(TokenKind::LBracket),
+            ']' => Some(TokenKind::RBracket),
+            '+' => Some(TokenKind::Plus),
+            '-' => Some(TokenKind::Minus),
+            _ => None,
+        };
+
+        if let Some(kind) = single {
+            tokens.push(Token { kind, column });
+            i += 1;
+        } else if c == ';' {
+            break;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text.parse().map_err(|_| {
+                AsmError::new(line_no, column, format!("Number is too big: {}", text))
+            })?;
+            tokens.push(Token {
+                kind: TokenKind::Number(value),
+                column,
+            });
+        } else if c.is_alphabetic() || c == '_' || c == '.' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token {
+                kind: TokenKind::Ident(chars[start..i].iter().collect()),
+                column,
+            });
+        } else if c == '"' {
+            let mut value = Vec::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(AsmError::new(line_no, column, "Unterminated string")),
+                    Some('"') => break,
+                    Some('\\') => {
+                        let escaped = match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('\\') => '\\',
+                            Some('"') => '"',
+                            _ => {
+                                return Err(AsmError::new(
+                                    line_no,
+                                    i + 1,
+                                    "Unknown escape sequence",
+                                ))
+                            }
+                        };
+                        value.push(escaped as DataType);
+                        i += 2;
+                    }
+                    Some(ch) => {
+                        value.push(*ch as DataType);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(Token {
+                kind: TokenKind::Str(value),
+                column,
+            });
+        } else {
+            return Err(AsmError::new(
+                line_no,
+                column,
+                format!("Unexpected character '{}'", c),
+            ));
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Number(DataType),
+    Symbol(String),
+}
+
+/// Sum of signed terms
+#[derive(Debug, Clone)]
+struct Expr {
+    terms: Vec<(DataType, Term, usize)>,
+}
+
+impl Expr {
+    fn number(value: DataType, column: usize) -> Self {
+        Self {
+            terms: vec![(1, Term::Number(value), column)],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+#[derive(Debug, Clone)]
+struct Operand {
+    mode: Mode,
+    expr: Expr,
+    column: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Instr {
+        opcode: DataType,
+        operands: Vec<Operand>,
+    },
+    Data(Vec<Expr>),
+}
+
+struct LineParser<'t> {
+    line: usize,
+    tokens: &'t [Token],
+    pos: usize,
+    end_column: usize,
+}
+
+impl<'t> LineParser<'t> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|t| t.column)
+            .unwrap_or(self.end_column)
+    }
+
+    fn error<S: Into<String>>(&self, message: S) -> AsmError {
+        AsmError::new(self.line, self.column(), message)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> AsmResult<()> {
+        if self.peek() == Some(&kind) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected {}", what)))
+        }
+    }
+
+    fn parse_term(&mut self) -> AsmResult<(Term, usize)> {
+        let column = self.column();
+        match self.next().map(|t| t.kind) {
+            Some(TokenKind::Number(value)) => Ok((Term::Number(value), column)),
+            Some(TokenKind::Ident(name)) => Ok((Term::Symbol(name), column)),
+            _ => Err(AsmError::new(
+                self.line,
+                column,
+                "Expected number or symbol",
+            )),
+        }
+    }
+
+    fn parse_expr(&mut self) -> AsmResult<Expr> {
+        let mut terms = Vec::new();
+        let mut sign = match self.peek() {
+            Some(TokenKind::Minus) => {
+                self.pos += 1;
+                -1
+            }
+            Some(TokenKind::Plus) => {
+                self.pos += 1;
+                1
+            }
+            _ => 1,
+        };
+
+        loop {
+            let (term, column) = self.parse_term()?;
+            terms.push((sign, term, column));
+
+            sign = match self.peek() {
+                Some(TokenKind::Plus) => 1,
+                Some(TokenKind::Minus) => -1,
+                _ => break,
+            };
+            self.pos += 1;
+        }
+
+        Ok(Expr { terms })
+    }
+
+    fn parse_operand(&mut self) -> AsmResult<Operand> {
+        let column = self.column();
+        let (mode, expr) = match self.peek() {
+            Some(TokenKind::Hash) => {
+                self.pos += 1;
+                (Mode::Immediate, self.parse_expr()?)
+            }
+            Some(TokenKind::LBracket) => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect(TokenKind::RBracket, "']'")?;
+                (Mode::Position, expr)
+            }
+            Some(TokenKind::Ident(name)) if name.eq_ignore_ascii_case("rb") => {
+                self.pos += 1;
+                match self.peek() {
+                    Some(TokenKind::Plus) | Some(TokenKind::Minus) => {
+                        (Mode::Relative, self.parse_expr()?)
+                    }
+                    _ => (Mode::Relative, Expr::number(0, column)),
+                }
+            }
+            _ => {
+                return Err(self.error("Expected operand: #imm, [pos] or rb+off"));
+            }
+        };
+
+        Ok(Operand { mode, expr, column })
+    }
+
+    /// Operand of a jump macro: bare expression means immediate target address
+    fn parse_target(&mut self) -> AsmResult<Operand> {
+        let bare = match self.peek() {
+            Some(TokenKind::Ident(name)) => !name.eq_ignore_ascii_case("rb"),
+            Some(TokenKind::Number(_)) => true,
+            _ => false,
+        };
+
+        if !bare {
+            return self.parse_operand();
+        }
+
+        let column = self.column();
+        Ok(Operand {
+            mode: Mode::Immediate,
+            expr: self.parse_expr()?,
+            column,
+        })
+    }
+
+    fn parse_list<T, F>(&mut self, mut parse: F) -> AsmResult<Vec<T>>
+    where
+        F: FnMut(&mut Self) -> AsmResult<T>,
+    {
+        let mut result = Vec::new();
+
+        if self.at_end() {
+            return Ok(result);
+        }
+
+        loop {
+            result.push(parse(self)?);
+            if self.at_end() {
+                break;
+            }
+            self.expect(TokenKind::Comma, "','")?;
+        }
+
+        Ok(result)
+    }
+}
+
+struct Instr {
+    base: DataType,
+    arity: usize,
+    /// Index of the parameter that is written to
+    dest: Option<usize>,
+}
+
+fn find_instr(mnemonic: &str) -> Option<Instr> {
+    let (base, arity, dest) = match mnemonic.to_ascii_uppercase().as_str() {
+        "ADD" => (1, 3, Some(2)),
+        "MUL" => (2, 3, Some(2)),
+        "GET" => (3, 1, Some(0)),
+        "SET" => (4, 1, None),
+        "JIT" => (5, 2, None),
+        "JIF" => (6, 2, None),
+        "LT" => (7, 3, Some(2)),
+        "EQ" => (8, 3, Some(2)),
+        "ARB" => (9, 1, None),
+        "EXIT" => (99, 0, None),
+        _ => return None,
+    };
+
+    Some(Instr { base, arity, dest })
+}
+
+enum Symbol {
+    Label(usize),
+    Const(Expr, usize, usize),
+}
+
+#[derive(Default)]
+pub struct Assembler {
+    symbols: HashMap<String, Symbol>,
+    items: Vec<(usize, Item)>,
+    size: usize,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn assemble(mut self, src: &str) -> AsmResult<Vec<DataType>> {
+        for (idx, line) in src.lines().enumerate() {
+            self.parse_line(idx + 1, line)?;
+        }
+
+        let mut prog = Vec::with_capacity(self.size);
+
+        for (line, item) in &self.items {
+            match item {
+                Item::Instr { opcode, operands } => {
+                    prog.push(*opcode);
+                    for operand in operands {
+                        prog.push(self.eval(*line, &operand.expr, 0)?);
+                    }
+                }
+                Item::Data(values) => {
+                    for value in values {
+                        prog.push(self.eval(*line, value, 0)?);
+                    }
+                }
+            }
+        }
+
+        Ok(prog)
+    }
+
+    fn define(
+        &mut self,
+        line: usize,
+        column: usize,
+        name: String,
+        symbol: Symbol,
+    ) -> AsmResult<()> {
+        if find_instr(&name).is_some() || name.eq_ignore_ascii_case("rb") {
+            return Err(AsmError::new(
+                line,
+                column,
+                format!("Reserved name '{}'", name),
+            ));
+        }
+        if self.symbols.contains_key(&name) {
+            return Err(AsmError::new(
+                line,
+                column,
+                format!("Symbol '{}' is already defined", name),
+            ));
+        }
+        self.symbols.insert(name, symbol);
+        Ok(())
+    }
+
+    fn push_item(&mut self, line: usize, item: Item) {
+        self.size += match &item {
+            Item::Instr { operands, .. } => operands.len() + 1,
+            Item::Data(values) => values.len(),
+        };
+        self.items.push((line, item));
+    }
+
+    fn push_instr(&mut self, line: usize, mnemonic: &str, operands: Vec<Operand>) -> AsmResult<()> {
+        let column = operands.first().map(|o| o.column).unwrap_or(1);
+        let instr = find_instr(mnemonic).ok_or_else(|| {
+            AsmError::new(line, column, format!("Unknown instruction {}", mnemonic))
+        })?;
+        let mut opcode = instr.base;
+        let mut factor = 100;
+
+        for (idx, operand) in operands.iter().enumerate() {
+            if instr.dest == Some(idx) && operand.mode == Mode::Immediate {
+                return Err(AsmError::new(
+                    line,
+                    operand.column,
+                    "Destination parameter cannot be immediate",
+                ));
+            }
+            opcode += factor
+                * match operand.mode {
+                    Mode::Position => 0,
+                    Mode::Immediate => 1,
+                    Mode::Relative => 2,
+                };
+            factor *= 10;
+        }
+
+        self.push_item(line, Item::Instr { opcode, operands });
+        Ok(())
+    }
+
+    fn parse_line(&mut self, line: usize, text: &str) -> AsmResult<()> {
+        let tokens = tokenize(line, text)?;
+        let mut parser = LineParser {
+            line,
+            tokens: &tokens,
+            pos: 0,
+            end_column: text.chars().count() + 1,
+        };
+
+        // Labels
+        while let (Some(TokenKind::Ident(name)), Some(TokenKind::Colon)) = (
+            tokens.get(parser.pos).map(|t| &t.kind),
+            tokens.get(parser.pos + 1).map(|t| &t.kind),
+        ) {
+            let column = parser.column();
+            self.define(line, column, name.clone(), Symbol::Label(self.size))?;
+            parser.pos += 2;
+        }
+
+        if parser.at_end() {
+            return Ok(());
+        }
+
+        let column = parser.column();
+        let name = match parser.next().map(|t| t.kind) {
+            Some(TokenKind::Ident(name)) => name,
+            _ => {
+                return Err(AsmError::new(
+                    line,
+                    column,
+                    "Expected instruction or directive",
+                ))
+            }
+        };
+
+        // Constant definition
+        if let Some(TokenKind::Ident(directive)) = parser.peek() {
+            if directive.eq_ignore_ascii_case("equ") {
+                parser.pos += 1;
+                let expr = parser.parse_expr()?;
+                if !parser.at_end() {
+                    return Err(parser.error("Unexpected token after expression"));
+                }
+                return self.define(line, column, name, Symbol::Const(expr, line, column));
+            }
+        }
+
+        match name.to_ascii_lowercase().as_str() {
+            "db" => {
+                let values = parser.parse_list(|p| match p.peek() {
+                    Some(TokenKind::Str(chars)) => {
+                        let column = p.column();
+                        let chars = chars.clone();
+                        p.pos += 1;
+                        Ok(chars.into_iter().map(|c| Expr::number(c, column)).collect())
+                    }
+                    _ => Ok(vec![p.parse_expr()?]),
+                })?;
+                self.push_item(line, Item::Data(values.into_iter().flatten().collect()));
+            }
+            "ds" => {
+                let count_column = parser.column();
+                let count = match parser.next().map(|t| t.kind) {
+                    Some(TokenKind::Number(count)) if count >= 0 => count as usize,
+                    _ => return Err(AsmError::new(line, count_column, "Expected cell count")),
+                };
+                // Program can't be larger than the memory of the computer
+                if count > (DEFAULT_MAX_ADDR + 1).saturating_sub(self.size) {
+                    return Err(AsmError::new(
+                        line,
+                        count_column,
+                        format!(
+                            "Cell count {} exceeds memory limit {}",
+                            count,
+                            DEFAULT_MAX_ADDR + 1
+                        ),
+                    ));
+                }
+                self.push_item(line, Item::Data(vec![Expr::number(0, count_column); count]));
+            }
+            "push" | "pop" | "call" | "ret" => {
+                let operands = if name.eq_ignore_ascii_case("call") {
+                    parser.parse_list(|p| p.parse_target())?
+                } else {
+                    parser.parse_list(|p| p.parse_operand())?
+                };
+                self.expand_macro(line, column, &name.to_ascii_lowercase(), operands)?;
+            }
+            _ => {
+                let instr = find_instr(&name).ok_or_else(|| {
+                    AsmError::new(line, column, format!("Unknown instruction '{}'", name))
+                })?;
+                let operands = parser.parse_list(|p| p.parse_operand())?;
+                if operands.len() != instr.arity {
+                    return Err(AsmError::new(
+                        line,
+                        column,
+                        format!(
+                            "{} expects {} operand(s) but {} given",
+                            name.to_ascii_uppercase(),
+                            instr.arity,
+                            operands.len()
+                        ),
+                    ));
+                }
+                self.push_instr(line, &name, operands)?;
+            }
+        }
+
+        if !parser.at_end() {
+            return Err(parser.error("Unexpected token"));
+        }
+
+        Ok(())
+    }
+
+    fn expand_macro(
+        &mut self,
+        line: usize,
+        column: usize,
+        name: &str,
+        mut operands: Vec<Operand>,
+    ) -> AsmResult<()> {
+        let expected = if name == "ret" { 0 } else { 1 };
+        if operands.len() != expected {
+            return Err(AsmError::new(
+                line,
+                column,
+                format!(
+                    "{} expects {} operand(s) but {} given",
+                    name.to_ascii_uppercase(),
+                    expected,
+                    operands.len()
+                ),
+            ));
+        }
+
+        let op = |mode, value| Operand {
+            mode,
+            expr: Expr::number(value, column),
+            column,
+        };
+
+        match name {
+            "push" => {
+                let value = operands.remove(0);
+                self.push_instr(
+                    line,
+                    "ADD",
+                    vec![value, op(Mode::Immediate, 0), op(Mode::Relative, 0)],
+                )?;
+                self.push_instr(line, "ARB", vec![op(Mode::Immediate, 1)])?;
+            }
+            "pop" => {
+                let dest = operands.remove(0);
+                self.push_instr(line, "ARB", vec![op(Mode::Immediate, -1)])?;
+                self.push_instr(
+                    line,
+                    "ADD",
+                    vec![op(Mode::Relative, 0), op(Mode::Immediate, 0), dest],
+                )?;
+            }
+            "call" => {
+                let target = operands.remove(0);
+                // ADD(4) + ARB(2) + JIT(3)
+                let ret_addr = (self.size + 9) as DataType;
+                self.push_instr(
+                    line,
+                    "ADD",
+                    vec![
+                        op(Mode::Immediate, ret_addr),
+                        op(Mode::Immediate, 0),
+                        op(Mode::Relative, 0),
+                    ],
+                )?;
+                self.push_instr(line, "ARB", vec![op(Mode::Immediate, 1)])?;
+                self.push_instr(line, "JIT", vec![op(Mode::Immediate, 1), target])?;
+            }
+            _ => {
+                self.push_instr(line, "ARB", vec![op(Mode::Immediate, -1)])?;
+                self.push_instr(
+                    line,
+                    "JIT",
+                    vec![op(Mode::Immediate, 1), op(Mode::Relative, 0)],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn eval(&self, line: usize, expr: &Expr, depth: usize) -> AsmResult<DataType> {
+        let mut result: DataType = 0;
+
+        for (sign, term, column) in &expr.terms {
+            let value = match term {
+                Term::Number(value) => *value,
+                Term::Symbol(name) => match self.symbols.get(name) {
+                    Some(Symbol::Label(addr)) => *addr as DataType,
+                    Some(Symbol::Const(expr, line, column)) => {
+                        if depth > self.symbols.len() {
+                            return Err(AsmError::new(
+                                *line,
+                                *column,
+                                format!("Recursive constant '{}'", name),
+                            ));
+                        }
+                        self.eval(*line, expr, depth + 1)?
+                    }
+                    None => {
+                        return Err(AsmError::new(
+                            line,
+                            *column,
+                            format!("Unknown symbol '{}'", name),
+                        ))
+                    }
+                },
+            };
+            result = sign
+                .checked_mul(value)
+                .and_then(|v| result.checked_add(v))
+                .ok_or_else(|| AsmError::new(line, *column, "Arithmetic overflow"))?;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+
+    fn run(src: &str, input: Vec<DataType>) -> Result<Vec<DataType>> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(Vec::new(), &log);
+        comp.load_prog(&to_prog_str(&assemble(src)?))?;
+        comp.add_input_vec(&mut input.clone());
+        comp.exec()?;
+        Ok(comp.get_output())
+    }
+
+    #[test]
+    fn test_encoding() -> Result<()> {
+        let prog = assemble(
+            "
+            N equ 2 + 1      ; constant
+            start: MUL [val], #N, rb-1
+                   EXIT
+            val:   db 33, N, \"A\"
+            ",
+        )?;
+
+        assert_eq!(prog, vec![21002, 5, 3, -1, 99, 33, 3, 65]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run() -> Result<()> {
+        let src = "
+            loop: GET  [x]
+                  JIF  [x], #end
+                  MUL  [x], #2, [x]
+                  SET  [x]
+                  JIT  #1, #loop
+            end:  EXIT
+            x:    ds 1
+        ";
+
+        assert_eq!(run(src, vec![1, 5, -7, 0])?, vec![2, 10, -14]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_macros() -> Result<()> {
+        let src = "
+                  ARB  #stack
+                  GET  [x]
+                  PUSH [x]
+                  CALL square
+                  POP  [x]
+                  SET  [x]
+                  EXIT
+            ; square argument in place, return address is at rb-1
+            square:
+                  MUL  rb-2, rb-2, rb-2
+                  RET
+            x:    db 0
+            stack:
+        ";
+
+        assert_eq!(run(src, vec![-12])?, vec![144]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |src: &str| Assembler::new().assemble(src).unwrap_err();
+
+        assert_eq!(
+            err("ADD #1, #2, #3"),
+            AsmError::new(1, 13, "Destination parameter cannot be immediate")
+        );
+        assert_eq!(
+            err("\n  JIT #1, #nowhere").to_string(),
+            "2:12: Unknown symbol 'nowhere'"
+        );
+        assert_eq!(
+            err("  FOO [1]").to_string(),
+            "1:3: Unknown instruction 'FOO'"
+        );
+        assert_eq!(err("SET [1] [2]").to_string(), "1:9: Expected ','");
+        assert_eq!(
+            err("a: EXIT\na: EXIT").to_string(),
+            "2:1: Symbol 'a' is already defined"
+        );
+        assert_eq!(
+            err("  SET\n").to_string(),
+            "1:3: SET expects 1 operand(s) but 0 given"
+        );
+        assert_eq!(
+            err("  ds 16777217").to_string(),
+            "1:6: Cell count 16777217 exceeds memory limit 16777216"
+        );
+        assert_eq!(err("ds -1").to_string(), "1:4: Expected cell count");
+        assert!(err("A equ B\nB equ A\nSET #A")
+            .message
+            .starts_with("Recursive constant"));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::intcode_comp::*;
+use crate::intcode_error::*;
+use crate::intcode_limits::*;
+use crate::intcode_loader::parse_values;
+use crate::intcode_memory::DEFAULT_MAX_ADDR;
+use anyhow::{ensure, Result};
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::collections::VecDeque;
+
+/// Intcode machine with arbitrary precision values. Runs the standard instruction set
+/// with the same faults as `IntcodeComp`. Values that don't fit `DataType` are reported
+/// in faults saturated to `DataType::MIN` or `DataType::MAX`.
+#[derive(Debug, Clone)]
+pub struct BigIntcodeComp {
+    mem: Vec<BigInt>,
+    max_addr: usize,
+    ip: usize,
+    /// Address of the EXIT instruction once the program is halted
+    halt_ip: Option<usize>,
+    rel_base: usize,
+    input: VecDeque<BigInt>,
+    output: Vec<BigInt>,
+    status: Status,
+    steps: u64,
+    max_steps: Option<u64>,
+}
+
+impl BigIntcodeComp {
+    pub fn new(prog: Vec<BigInt>) -> Self {
+        Self {
+            mem: prog,
+            max_addr: DEFAULT_MAX_ADDR,
+            ip: 0,
+            halt_ip: None,
+            rel_base: 0,
+            input: VecDeque::new(),
+            output: Vec::new(),
+            status: Status::Running,
+            steps: 0,
+            max_steps: None,
+        }
+    }
+
+    /// Program text in the `intcode_loader` format, values may exceed `DataType`
+    pub fn from_prog(prog: &str) -> Result<Self> {
+        Ok(Self::new(parse_values(prog)?))
+    }
+
+    pub fn add_input<V: Into<BigInt>>(&mut self, value: V) {
+        self.input.push_back(value.into());
+    }
+
+    pub fn output(&self) -> &[BigInt] {
+        &self.output
+    }
+
+    pub fn get_output(&mut self) -> Vec<BigInt> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Memory up to the highest written address
+    pub fn memory(&self) -> &[BigInt] {
+        &self.mem
+    }
+
+    pub fn get_mem(&self, addr: usize) -> BigInt {
+        self.mem.get(addr).cloned().unwrap_or_default()
+    }
+
+    pub fn get_ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn get_rel_base(&self) -> usize {
+        self.rel_base
+    }
+
+    pub fn get_status(&self) -> Status {
+        self.status.clone()
+    }
+
+    pub fn get_steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.status == Status::Halted
+    }
+
+    pub fn set_max_addr(&mut self, max_addr: usize) {
+        self.max_addr = max_addr;
+    }
+
+    pub fn set_instruction_limit(&mut self, max_steps: Option<u64>) {
+        self.max_steps = max_steps;
+    }
+
+    /// Run whole program
+    pub fn exec(&mut self) -> Result<()> {
+        self.run()?;
+
+        ensure!(
+            self.is_halted(),
+            "Program was not finished properly. Status: {:?}",
+            self.status
+        );
+
+        Ok(())
+    }
+
+    /// Run until halt, next input or instruction limit
+    pub fn run(&mut self) -> Result<()> {
+        if self.is_halted() {
+            let ip = self.halt_ip.unwrap_or(self.ip);
+            return Err(IntcodeError::Halted {
+                ip,
+                opcode: saturated(&self.get_mem(ip)),
+            }
+            .into());
+        }
+
+        self.status = Status::Running;
+
+        while self.status == Status::Running {
+            if self.max_steps.is_some_and(|max| self.steps >= max) {
+                self.status = Status::Interrupted(Interrupt::InstructionLimit);
+                break;
+            }
+            self.eval_cmd()?;
+        }
+
+        Ok(())
+    }
+
+    fn error<F>(&self, f: F) -> anyhow::Error
+    where
+        F: FnOnce(usize, DataType) -> IntcodeError,
+    {
+        f(self.ip, saturated(&self.get_mem(self.ip))).into()
+    }
+
+    fn check_addr(&self, addr: &BigInt) -> Result<usize> {
+        if addr.is_negative() {
+            let addr = saturated(addr);
+            return Err(self.error(|ip, opcode| IntcodeError::NegativeAddress {
+                ip,
+                opcode,
+                addr,
+            }));
+        }
+
+        match addr.to_usize().filter(|addr| *addr <= self.max_addr) {
+            Some(addr) => Ok(addr),
+            None => {
+                let (addr, max_addr) = (saturated(addr), self.max_addr);
+                Err(self.error(|ip, opcode| IntcodeError::MemoryLimit {
+                    ip,
+                    opcode,
+                    addr,
+                    max_addr,
+                }))
+            }
+        }
+    }
+
+    fn param_addr(&self, offset: usize, mode: ParamMode) -> Result<usize> {
+        let raw = self.get_mem(self.ip + offset);
+        match mode {
+            ParamMode::Position => self.check_addr(&raw),
+            ParamMode::Relative => self.check_addr(&(raw + self.rel_base)),
+            _ => Err(self.error(|ip, opcode| IntcodeError::WriteToImmediate { ip, opcode })),
+        }
+    }
+
+    fn get_param(&self, offset: usize, mode: ParamMode) -> Result<BigInt> {
+        match mode {
+            ParamMode::Immediate => Ok(self.get_mem(self.ip + offset)),
+            _ => Ok(self.get_mem(self.param_addr(offset, mode)?)),
+        }
+    }
+
+    fn set_param(&mut self, offset: usize, mode: ParamMode, value: BigInt) -> Result<()> {
+        let addr = self.param_addr(offset, mode)?;
+        if addr >= self.mem.len() {
+            self.mem.resize(addr + 1, BigInt::zero());
+        }
+        self.mem[addr] = value;
+        Ok(())
+    }
+
+    fn eval_cmd(&mut self) -> Result<()> {
+        let opcode = self.get_mem(self.ip);
+        let (cmd, params_count) = match opcode.to_i64() {
+            Some(opcode) => Command::parse(self.ip, opcode)?,
+            None => return Err(self.error(|ip, opcode| IntcodeError::InvalidOpcode { ip, opcode })),
+        };
+
+        let mut next_ip = self.ip + params_count + 1;
+        let flag = |cond: bool| if cond { BigInt::one() } else { BigInt::zero() };
+
+        match cmd {
+            Command::Add(m1, m2, m3) => {
+                let value = self.get_param(1, m1)? + self.get_param(2, m2)?;
+                self.set_param(3, m3, value)?;
+            }
+            Command::Mul(m1, m2, m3) => {
+                let value = self.get_param(1, m1)? * self.get_param(2, m2)?;
+                self.set_param(3, m3, value)?;
+            }
+            Command::Read(m1) => {
+                let value = match self.input.pop_front() {
+                    Some(value) => value,
+                    None => {
+                        self.status = Status::WaitForInput;
+                        return Ok(());
+                    }
+                };
+                self.set_param(1, m1, value)?;
+            }
+            Command::Write(m1) => {
+                let value = self.get_param(1, m1)?;
+                self.output.push(value);
+            }
+            Command::JumpIfTrue(m1, m2) | Command::JumpIfFalse(m1, m2) => {
+                let jump_if = matches!(cmd, Command::JumpIfTrue(..));
+                if self.get_param(1, m1)?.is_zero() != jump_if {
+                    next_ip = self.check_addr(&self.get_param(2, m2)?)?;
+                }
+            }
+            Command::LessThan(m1, m2, m3) => {
+                let value = flag(self.get_param(1, m1)? < self.get_param(2, m2)?);
+                self.set_param(3, m3, value)?;
+            }
+            Command::Equals(m1, m2, m3) => {
+                let value = flag(self.get_param(1, m1)? == self.get_param(2, m2)?);
+                self.set_param(3, m3, value)?;
+            }
+            Command::AdjustRelBase(m1) => {
+                let rel_base = self.get_param(1, m1)? + self.rel_base;
+                self.rel_base = self.check_addr(&rel_base)?;
+            }
+            Command::Exit => {
+                self.status = Status::Halted;
+                self.halt_ip = Some(self.ip);
+            }
+            Command::Ext(_) => {
+                return Err(self.error(|ip, opcode| IntcodeError::InvalidOpcode { ip, opcode }))
+            }
+        }
+
+        self.ip = next_ip;
+        self.steps += 1;
+
+        Ok(())
+    }
+}
+
+/// Nearest `DataType` value
+fn saturated(value: &BigInt) -> DataType {
+    value.to_i64().unwrap_or(if value.is_negative() {
+        DataType::MIN
+    } else {
+        DataType::MAX
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_values() -> Result<()> {
+        // MUL #MAX, #MAX, [11]; SET [11]; MUL [11], [11], [11]
+        let max = DataType::MAX.to_string();
+        let prog = format!("1102,{0},{0},11,4,11,2,11,11,11,99,0", max);
+        let mut comp = BigIntcodeComp::from_prog(&prog)?;
+        comp.set_instruction_limit(Some(3));
+        comp.run()?;
+
+        let square = BigInt::from(DataType::MAX) * DataType::MAX;
+        assert_eq!(comp.get_output(), vec![square.clone()]);
+        assert_eq!(comp.get_mem(11), &square * &square);
+        assert_eq!(
+            comp.get_status(),
+            Status::Interrupted(Interrupt::InstructionLimit)
+        );
+
+        // Sum of two inputs beyond i64
+        let mut comp = BigIntcodeComp::from_prog("3,11,3,12,1,11,12,13,4,13,99")?;
+        let big: BigInt = "1000000000000000000000000000000".parse()?;
+        comp.add_input(big.clone());
+        comp.add_input(big.clone());
+        comp.exec()?;
+        assert_eq!(comp.get_output(), vec![big * 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_faults() -> Result<()> {
+        let huge = "100000000000000000000";
+
+        let mut comp = BigIntcodeComp::from_prog(&format!("4,{},99", huge))?;
+        assert_eq!(
+            comp.exec().unwrap_err().downcast::<IntcodeError>()?,
+            IntcodeError::MemoryLimit {
+                ip: 0,
+                opcode: 4,
+                addr: DataType::MAX,
+                max_addr: DEFAULT_MAX_ADDR
+            }
+        );
+
+        let mut comp = BigIntcodeComp::from_prog(&format!("-{}", huge))?;
+        assert_eq!(
+            comp.exec().unwrap_err().downcast::<IntcodeError>()?,
+            IntcodeError::InvalidOpcode {
+                ip: 0,
+                opcode: DataType::MIN
+            }
+        );
+
+        let mut comp = BigIntcodeComp::from_prog("3,0,99")?;
+        comp.run()?;
+        assert_eq!(comp.get_status(), Status::WaitForInput);
+        comp.add_input(99);
+        comp.run()?;
+        assert!(comp.is_halted());
+        assert!(comp.run().is_err());
+        Ok(())
+    }
+}
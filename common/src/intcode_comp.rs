@@ -1,8 +1,21 @@
+use crate::intcode_decode::*;
+use crate::intcode_device::*;
+use crate::intcode_error::*;
+use crate::intcode_isa::*;
+use crate::intcode_journal::*;
+use crate::intcode_limits::*;
+use crate::intcode_loader::parse_prog;
+use crate::intcode_memory::*;
+use crate::intcode_profile::*;
+use crate::intcode_snapshot::*;
+use crate::intcode_trace::*;
 use crate::log::*;
-use anyhow::{bail, ensure, Context, Result};
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 
-#[derive(Debug)]
-enum Command {
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Command {
     Add(ParamMode, ParamMode, ParamMode),      // 1
     Mul(ParamMode, ParamMode, ParamMode),      // 2
     Read(ParamMode),                           // 3
@@ -13,74 +26,141 @@ enum Command {
     Equals(ParamMode, ParamMode, ParamMode),   // 8
     AdjustRelBase(ParamMode),                  // 9
     Exit,                                      // 99
+    /// Opcode registered in the `InstructionSet`
+    Ext(ExtCommand),
 }
 
 impl Command {
-    fn parse(opc: DataType) -> Result<(Self, usize)> {
+    /// Decode opcode located at `ip` into command and number of its parameters
+    pub fn parse(ip: usize, opc: DataType) -> std::result::Result<(Self, usize), IntcodeError> {
+        Self::parse_with(ip, opc, |_| false)
+    }
+
+    /// Same as `parse` but parameter modes accepted by `custom` are decoded
+    /// into `ParamMode::Custom`
+    pub fn parse_with<F>(
+        ip: usize,
+        opc: DataType,
+        custom: F,
+    ) -> std::result::Result<(Self, usize), IntcodeError>
+    where
+        F: Fn(DataType) -> bool,
+    {
         let cmd_id = opc % 100;
-        let params = opc / 100;
         let cmd = match cmd_id {
             1 => {
-                let params = ParamMode::parse(params, 3)?;
+                let params = ParamMode::parse_with(ip, opc, 3, &custom)?;
                 (Command::Add(params[0], params[1], params[2]), 3)
             }
             2 => {
-                let params = ParamMode::parse(params, 3)?;
+                let params = ParamMode::parse_with(ip, opc, 3, &custom)?;
                 (Command::Mul(params[0], params[1], params[2]), 3)
             }
             3 => {
-                let params = ParamMode::parse(params, 1)?;
+                let params = ParamMode::parse_with(ip, opc, 1, &custom)?;
                 (Command::Read(params[0]), 1)
             }
             4 => {
-                let params = ParamMode::parse(params, 1)?;
+                let params = ParamMode::parse_with(ip, opc, 1, &custom)?;
                 (Command::Write(params[0]), 1)
             }
             5 => {
-                let params = ParamMode::parse(params, 2)?;
+                let params = ParamMode::parse_with(ip, opc, 2, &custom)?;
                 (Command::JumpIfTrue(params[0], params[1]), 2)
             }
             6 => {
-                let params = ParamMode::parse(params, 2)?;
+                let params = ParamMode::parse_with(ip, opc, 2, &custom)?;
                 (Command::JumpIfFalse(params[0], params[1]), 2)
             }
             7 => {
-                let params = ParamMode::parse(params, 3)?;
+                let params = ParamMode::parse_with(ip, opc, 3, &custom)?;
                 (Command::LessThan(params[0], params[1], params[2]), 3)
             }
             8 => {
-                let params = ParamMode::parse(params, 3)?;
+                let params = ParamMode::parse_with(ip, opc, 3, &custom)?;
                 (Command::Equals(params[0], params[1], params[2]), 3)
             }
             9 => {
-                let params = ParamMode::parse(params, 1)?;
+                let params = ParamMode::parse_with(ip, opc, 1, &custom)?;
                 (Command::AdjustRelBase(params[0]), 1)
             }
             99 => (Command::Exit, 0),
-            _ => bail!("Unknown command id {}.", opc % 100),
+            _ => return Err(IntcodeError::InvalidOpcode { ip, opcode: opc }),
         };
 
         Ok(cmd)
     }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Command::Add(..) => "ADD",
+            Command::Mul(..) => "MUL",
+            Command::Read(..) => "GET",
+            Command::Write(..) => "SET",
+            Command::JumpIfTrue(..) => "JIT",
+            Command::JumpIfFalse(..) => "JIF",
+            Command::LessThan(..) => "LT",
+            Command::Equals(..) => "EQ",
+            Command::AdjustRelBase(..) => "ARB",
+            Command::Exit => "EXIT",
+            Command::Ext(ext) => ext.mnemonic,
+        }
+    }
+
+    pub fn param_modes(&self) -> Vec<ParamMode> {
+        match *self {
+            Command::Add(m1, m2, m3)
+            | Command::Mul(m1, m2, m3)
+            | Command::LessThan(m1, m2, m3)
+            | Command::Equals(m1, m2, m3) => vec![m1, m2, m3],
+            Command::JumpIfTrue(m1, m2) | Command::JumpIfFalse(m1, m2) => vec![m1, m2],
+            Command::Read(m1) | Command::Write(m1) | Command::AdjustRelBase(m1) => vec![m1],
+            Command::Exit => Vec::new(),
+            Command::Ext(ext) => ext.param_modes().to_vec(),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-enum ParamMode {
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+pub enum ParamMode {
     Position,  // 0
     Immediate, // 1
     Relative,  // 2
+    /// Mode registered in the `InstructionSet`
+    Custom(u8),
 }
 
 impl ParamMode {
-    fn parse(opcode: DataType, count: u8) -> Result<Vec<ParamMode>> {
+    /// Decode modes of `count` parameters of the opcode located at `ip`
+    pub fn parse(
+        ip: usize,
+        opcode: DataType,
+        count: u8,
+    ) -> std::result::Result<Vec<ParamMode>, IntcodeError> {
+        Self::parse_with(ip, opcode, count, |_| false)
+    }
+
+    /// Same as `parse` but modes accepted by `custom` are decoded into `ParamMode::Custom`
+    pub fn parse_with<F>(
+        ip: usize,
+        opcode: DataType,
+        count: u8,
+        custom: F,
+    ) -> std::result::Result<Vec<ParamMode>, IntcodeError>
+    where
+        F: Fn(DataType) -> bool,
+    {
         let mut result = Vec::new();
-        let mut opc = opcode;
+        let mut opc = opcode / 100;
         for _i in 0..count {
             let param_mode = match opc % 10 {
                 0 => ParamMode::Position,
                 1 => ParamMode::Immediate,
                 2 => ParamMode::Relative,
-                _ => bail!("Unknown parameter mode {}", opc % 10),
+                mode if custom(mode) => ParamMode::Custom(mode as u8),
+                mode => {
+                    return Err(IntcodeError::InvalidParamMode { ip, opcode, mode });
+                }
             };
             result.push(param_mode);
             opc /= 10;
@@ -89,74 +169,192 @@ impl ParamMode {
     }
 }
 
-#[derive(PartialEq, Clone, Debug)]
-enum Status {
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum Status {
     Running,
     WaitForInput,
     Halted,
+    /// Stopped by one of the execution limits. Can be resumed by the next run call.
+    Interrupted(Interrupt),
 }
 
 pub type DataType = i64;
 
+/// How ADD and MUL handle results not fitting `DataType`
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum Arithmetic {
+    /// Fault with `IntcodeError::Overflow`
+    Checked,
+    /// Two's complement wrap around, the default as before checked mode was added
+    #[default]
+    Wrapping,
+}
+
 #[derive(Clone)]
 pub struct IntcodeComp<'l> {
-    prog: Vec<DataType>,
+    mem: Box<dyn Memory>,
+    max_addr: usize,
     prog_backup: Vec<DataType>,
     ip: usize,
+    /// Address of the instruction that halted the program
+    halt_ip: Option<usize>,
     rel_base: usize,
     input: Vec<DataType>,
     output: Vec<DataType>,
+    /// Output values taken by the host, see `JournalEntry::output_len`
+    output_taken: usize,
     status: Status,
+    steps: u64,
     log: &'l Log,
+    tracer: Option<SharedTraceSink>,
+    event: Option<TraceEvent>,
+    journal: Option<Journal>,
+    journal_entry: Option<JournalEntry>,
+    limits: Limits,
+    profile: Option<Profile>,
+    isa: InstructionSet,
+    cache: Option<DecodeCache>,
+    arithmetic: Arithmetic,
 }
 
 impl<'l> IntcodeComp<'l> {
     pub fn new(prog: Vec<DataType>, log: &'l Log) -> Self {
         log.println(format!("=> New comp. Size: {}", prog.len()));
+        let mut mem = FlatMemory::new();
+        mem.load(&prog);
         Self {
-            prog: prog.clone(),
+            mem: Box::new(mem),
+            max_addr: DEFAULT_MAX_ADDR,
             prog_backup: prog,
             ip: 0,
+            halt_ip: None,
             rel_base: 0,
             input: Vec::new(),
             output: Vec::new(),
+            output_taken: 0,
             status: Status::Running,
+            steps: 0,
             log,
+            tracer: None,
+            event: None,
+            journal: None,
+            journal_entry: None,
+            limits: Limits::default(),
+            profile: None,
+            isa: InstructionSet::default(),
+            cache: Some(DecodeCache::new()),
+            arithmetic: Arithmetic::default(),
         }
     }
 
-    pub fn load_prog(&mut self, data: &str) -> Result<()> {
-        if data.trim().is_empty() {
-            return Ok(());
+    /// Amplifier-like program reading its phase setting first
+    pub fn with_phase(prog: Vec<DataType>, phase: DataType, log: &'l Log) -> Self {
+        let mut comp = Self::new(prog, log);
+        comp.add_input(phase);
+        comp
+    }
+
+    /// Program text in the `intcode_loader` format
+    pub fn from_prog(prog: &str, log: &'l Log) -> Result<Self> {
+        let mut comp = Self::new(Vec::new(), log);
+        comp.load_prog(prog)?;
+        Ok(comp)
+    }
+
+    pub fn from_snapshot(snapshot: Snapshot, log: &'l Log) -> Result<Self> {
+        let mut comp = Self::new(Vec::new(), log);
+        comp.restore(snapshot)?;
+        Ok(comp)
+    }
+
+    /// Memory is stored as written segments, see `Memory::image`
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            memory: self.mem.image(),
+            prog_backup: self.prog_backup.clone(),
+            ip: self.ip,
+            halt_ip: self.halt_ip,
+            rel_base: self.rel_base,
+            input: self.input.clone(),
+            output: self.output.clone(),
+            status: self.status.clone(),
+            steps: self.steps,
         }
+    }
 
-        let cmd_str: Vec<&str> = data.split(',').collect();
+    /// Replace whole machine state. Log and tracer are kept. Sparse memory image
+    /// switches flat memory to `PagedMemory`.
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<()> {
+        ensure!(
+            snapshot.version == SNAPSHOT_VERSION,
+            "Unsupported snapshot version {}. Expected {}.",
+            snapshot.version,
+            SNAPSHOT_VERSION
+        );
+        ensure!(
+            snapshot.memory.size() <= self.max_addr.saturating_add(1),
+            "Snapshot memory size {} exceeds memory limit {}.",
+            snapshot.memory.size(),
+            self.max_addr
+        );
+
+        if !snapshot.memory.is_dense() && !self.mem.is_sparse() {
+            self.mem = Box::new(PagedMemory::new());
+        }
+        self.mem.load_image(&snapshot.memory);
+        self.clear_cache();
+        self.prog_backup = snapshot.prog_backup;
+        self.ip = snapshot.ip;
+        self.halt_ip = snapshot.halt_ip;
+        self.rel_base = snapshot.rel_base;
+        self.input = snapshot.input;
+        self.output = snapshot.output;
+        self.status = snapshot.status;
+        self.steps = snapshot.steps;
+
+        self.log
+            .println(format!("=> Restore snapshot. Size: {}", self.mem.size()));
 
-        self.prog_backup.clear();
+        Ok(())
+    }
 
-        for cmd in cmd_str {
-            self.prog_backup.push(cmd.parse()?);
+    /// Program text in the `intcode_loader` format. Empty text keeps the current program.
+    pub fn load_prog(&mut self, data: &str) -> Result<()> {
+        let prog = parse_prog(data)?;
+        if prog.is_empty() {
+            return Ok(());
         }
 
-        self.prog = self.prog_backup.clone();
+        self.prog_backup = prog;
+        self.mem.load(&self.prog_backup);
+        self.clear_cache();
 
         self.log
-            .println(format!("=> Load prog. Size: {}", self.prog.len()));
+            .println(format!("=> Load prog. Size: {}", self.mem.size()));
 
         Ok(())
     }
 
     pub fn start(&mut self) {
         self.status = Status::Running;
+        self.halt_ip = None;
     }
 
     pub fn restart(&mut self) {
-        self.status = Status::Running;
+        self.start();
         self.ip = 0;
+        self.rel_base = 0;
+        self.steps = 0;
+
+        if let Some(profile) = &mut self.profile {
+            profile.end_block();
+        }
     }
 
     pub fn reset(&mut self) {
-        self.prog = self.prog_backup.clone();
+        self.mem.load(&self.prog_backup);
+        self.clear_cache();
         self.restart();
     }
 
@@ -168,10 +366,193 @@ impl<'l> IntcodeComp<'l> {
         self.status == Status::Halted
     }
 
+    pub fn is_interrupted(&self) -> bool {
+        matches!(self.status, Status::Interrupted(_))
+    }
+
     pub fn get_ip(&self) -> usize {
         self.ip
     }
 
+    pub fn get_rel_base(&self) -> usize {
+        self.rel_base
+    }
+
+    pub fn get_status(&self) -> Status {
+        self.status.clone()
+    }
+
+    /// Number of executed instructions
+    pub fn get_steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Send event for every executed instruction to the sink
+    pub fn set_tracer(&mut self, tracer: SharedTraceSink) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn clear_tracer(&mut self) {
+        self.tracer = None;
+    }
+
+    pub fn tracer(&self) -> Option<&SharedTraceSink> {
+        self.tracer.as_ref()
+    }
+
+    /// Interrupt execution when the number of executed instructions reaches the limit
+    pub fn set_instruction_limit(&mut self, max_steps: Option<u64>) {
+        self.limits.max_steps = max_steps;
+    }
+
+    /// Interrupt every run call lasting longer than the timeout
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.limits.timeout = timeout;
+    }
+
+    /// Interrupt execution when the token is cancelled
+    pub fn set_cancel_token(&mut self, cancel: Option<CancelToken>) {
+        self.limits.cancel = cancel;
+    }
+
+    pub fn get_limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Opcodes and parameter modes added to the standard ones
+    pub fn set_instruction_set(&mut self, isa: InstructionSet) {
+        self.isa = isa;
+        self.clear_cache();
+    }
+
+    pub fn instruction_set(&self) -> &InstructionSet {
+        &self.isa
+    }
+
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    pub fn get_arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Keep decoded instructions between executions. Enabled by default.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache = if enabled {
+            Some(DecodeCache::new())
+        } else {
+            None
+        };
+    }
+
+    pub fn decode_cache(&self) -> Option<&DecodeCache> {
+        self.cache.as_ref()
+    }
+
+    /// Count executed instructions, blocks and memory accesses. Previous profile is dropped.
+    pub fn start_profiling(&mut self) {
+        self.profile = Some(Profile::new());
+    }
+
+    pub fn stop_profiling(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Record executed instructions so execution can be stepped backwards
+    pub fn start_recording(&mut self, config: JournalConfig) {
+        self.journal = Some(Journal::new(config));
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.journal = None;
+    }
+
+    pub fn journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    /// Undo last executed instruction. Returns false if there is nothing to undo.
+    /// Output of the instruction is removed from the queue unless the host already took it.
+    pub fn step_back(&mut self) -> Result<bool> {
+        let journal = self
+            .journal
+            .as_mut()
+            .ok_or_else(|| anyhow!("Recording is not started."))?;
+
+        let entry = match journal.pop() {
+            Some(entry) => entry,
+            None => return Ok(false),
+        };
+
+        for (addr, value) in entry.writes.into_iter().rev() {
+            self.mem.set(addr, value);
+        }
+        self.mem.truncate(entry.mem_size);
+        self.clear_cache();
+
+        if let Some(value) = entry.input {
+            self.input.insert(0, value);
+        }
+        self.output
+            .truncate(entry.output_len.saturating_sub(self.output_taken));
+
+        self.ip = entry.ip;
+        self.rel_base = entry.rel_base;
+        self.status = entry.status;
+        self.steps = entry.step;
+
+        Ok(true)
+    }
+
+    /// Move execution to the state right before instruction number `step`.
+    /// Going forward stops earlier if program halts or waits for input.
+    /// Going back beyond journal entries restores the nearest checkpoint
+    /// (including its output queue) and re-runs the program with recorded input.
+    pub fn goto_step(&mut self, step: u64) -> Result<()> {
+        let journal = self
+            .journal
+            .as_mut()
+            .ok_or_else(|| anyhow!("Recording is not started."))?;
+
+        if step < self.steps {
+            let covered = journal
+                .entries()
+                .front()
+                .map(|e| e.step <= step)
+                .unwrap_or(false);
+
+            if covered {
+                while self.steps > step {
+                    self.step_back()?;
+                }
+                return Ok(());
+            }
+
+            let checkpoint = journal.checkpoint_before(step).cloned().ok_or_else(|| {
+                anyhow!(
+                    "Step {} is out of journal. Earliest step: {:?}.",
+                    step,
+                    journal.earliest_step()
+                )
+            })?;
+
+            let mut input = journal.rewind(checkpoint.steps);
+            input.append(&mut self.input);
+
+            self.restore(checkpoint)?;
+            self.input = input;
+        }
+
+        while self.steps < step && !self.is_halted() && self.step()? {}
+
+        Ok(())
+    }
+
     pub fn add_input(&mut self, input: DataType) {
         self.input.push(input);
     }
@@ -181,22 +562,55 @@ impl<'l> IntcodeComp<'l> {
         self.add_input(input);
     }
 
-    pub fn add_input_vec(&mut self, mut input: &mut Vec<DataType>) {
-        self.input.append(&mut input);
+    pub fn add_input_vec(&mut self, input: &mut Vec<DataType>) {
+        self.input.append(input);
+    }
+
+    /// Input values that were not consumed yet
+    pub fn input(&self) -> &[DataType] {
+        &self.input
+    }
+
+    /// Output values that were not taken by `get_output` yet
+    pub fn output(&self) -> &[DataType] {
+        &self.output
     }
 
     pub fn get_output(&mut self) -> Vec<DataType> {
-        let output = self.output.clone();
-        self.output.clear();
-        output
+        self.output_taken += self.output.len();
+        std::mem::take(&mut self.output)
     }
 
-    /// Run whole program and return outputs
+    /// Run whole program and return outputs. Interrupted program is resumed.
     pub fn exec(&mut self) -> Result<()> {
+        if let Status::Interrupted(_) = self.status {
+            self.status = Status::Running;
+        }
+
         while self.is_running() {
             self.run()?;
         }
 
+        if let Status::Interrupted(interrupt) = self.status {
+            return Err(match interrupt {
+                Interrupt::InstructionLimit => {
+                    let limit = self.limits.max_steps.unwrap_or_default();
+                    self.error(|ip, opcode| IntcodeError::InstructionLimit { ip, opcode, limit })
+                }
+                Interrupt::Timeout => {
+                    let timeout = self.limits.timeout.unwrap_or_default();
+                    self.error(|ip, opcode| IntcodeError::Timeout {
+                        ip,
+                        opcode,
+                        timeout,
+                    })
+                }
+                Interrupt::Cancelled => {
+                    self.error(|ip, opcode| IntcodeError::Cancelled { ip, opcode })
+                }
+            });
+        }
+
         ensure!(
             self.status == Status::Halted,
             "Program was not finished properly. Status: {:?}",
@@ -206,25 +620,73 @@ impl<'l> IntcodeComp<'l> {
         Ok(())
     }
 
+    /// Write memory cell. Max address limit is not applied to writes made by the host.
     pub fn set_mem(&mut self, addr: usize, value: DataType) {
-        self.check_and_extend(addr);
-        self.prog[addr] = value;
+        self.write_mem(addr, value);
+    }
+
+    /// Memory value. Cells that were never written are zeros.
+    pub fn get_mem(&self, addr: usize) -> DataType {
+        self.mem.get(addr)
+    }
+
+    /// Sparse copy of the memory
+    pub fn memory(&self) -> MemoryImage {
+        self.mem.image()
+    }
+
+    /// Highest written address + 1
+    pub fn mem_size(&self) -> usize {
+        self.mem.size()
+    }
+
+    /// Replace memory backend keeping its current content
+    pub fn set_memory(&mut self, mut memory: Box<dyn Memory>) {
+        memory.load_image(&self.mem.image());
+        self.mem = memory;
+        self.clear_cache();
+    }
+
+    /// Highest address the program is allowed to access
+    pub fn set_max_addr(&mut self, max_addr: usize) {
+        self.max_addr = max_addr;
+    }
+
+    pub fn get_max_addr(&self) -> usize {
+        self.max_addr
+    }
+
+    /// Execute single instruction. Returns false if program is halted, waits for input
+    /// or is interrupted. Timeout is not applied.
+    pub fn step(&mut self) -> Result<bool> {
+        if self.is_halted() {
+            return Err(self.halted_error());
+        }
+
+        self.status = Status::Running;
+
+        if self.interrupted(0, None) {
+            return Ok(false);
+        }
+
+        self.eval_cmd()
     }
 
     /// Run computer until next input
     pub fn run(&mut self) -> Result<()> {
-        ensure!(
-            !self.is_halted(),
-            "Program was halted. ip={} status={:?}.",
-            self.ip,
-            self.status
-        );
+        if self.is_halted() {
+            return Err(self.halted_error());
+        }
 
         self.status = Status::Running;
 
         self.log.println(format!("=> Input: {:?}", self.input));
 
-        while self.eval_cmd()? {}
+        let deadline = self.limits.deadline();
+        let mut count = 0;
+        while !self.interrupted(count, deadline) && self.eval_cmd()? {
+            count += 1;
+        }
 
         if !self.input.is_empty() {
             self.log.println(format!(
@@ -234,184 +696,543 @@ impl<'l> IntcodeComp<'l> {
             ));
         }
 
+        self.log.println(format!("=> Output: {:?}", self.output));
         self.log.println(format!("=> Status: {:?}", self.status));
 
         Ok(())
     }
 
-    fn rel_ip(&self, offset: DataType) -> usize {
-        (self.rel_base as DataType + offset) as usize
+    /// Add single input value, run until next input and return the output
+    pub fn run_with_input(&mut self, input: DataType) -> Result<Vec<DataType>> {
+        self.add_input(input);
+        self.run()?;
+        Ok(self.get_output())
+    }
+
+    /// Run computer until next output value. Values that were not taken yet are
+    /// returned first. None if program halts, waits for input or is interrupted
+    /// before writing anything.
+    pub fn run_until_output(&mut self) -> Result<Option<DataType>> {
+        if self.output.is_empty() && !self.is_halted() {
+            self.status = Status::Running;
+
+            let deadline = self.limits.deadline();
+            let mut count = 0;
+            while self.output.is_empty() && !self.interrupted(count, deadline) && self.eval_cmd()? {
+                count += 1;
+            }
+
+            self.log.println(format!(
+                "=> Output: {:?} Status: {:?}",
+                self.output, self.status
+            ));
+        }
+
+        if self.output.is_empty() {
+            return Ok(None);
+        }
+
+        self.output_taken += 1;
+        Ok(Some(self.output.remove(0)))
+    }
+
+    /// Run computer using the device for I/O. Queued input values are consumed first.
+    /// Stops when program halts, is interrupted or device has no input.
+    pub fn run_device(&mut self, device: &mut dyn Device) -> Result<()> {
+        if self.is_halted() {
+            return Err(self.halted_error());
+        }
+
+        self.status = Status::Running;
+
+        let deadline = self.limits.deadline();
+        let mut count = 0;
+
+        loop {
+            if self.interrupted(count, deadline) {
+                return Ok(());
+            }
+            count += 1;
+
+            let running = self.eval_cmd()?;
+
+            self.output_taken += self.output.len();
+            for value in self.output.drain(..) {
+                device.output(value)?;
+            }
+
+            if !running {
+                if self.status != Status::WaitForInput {
+                    return Ok(());
+                }
+
+                match device.input()? {
+                    Some(value) => {
+                        self.input.push(value);
+                        self.status = Status::Running;
+                    }
+                    None => return Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Next queued input value
+    pub(crate) fn take_input(&mut self) -> Option<DataType> {
+        if self.input.is_empty() {
+            return None;
+        }
+
+        let value = self.input.remove(0);
+        if let Some(event) = &mut self.event {
+            event.input = Some(value);
+        }
+        if let Some(entry) = &mut self.journal_entry {
+            entry.input = Some(value);
+        }
+
+        Some(value)
+    }
+
+    pub(crate) fn push_output(&mut self, value: DataType) {
+        if let Some(event) = &mut self.event {
+            event.output = Some(value);
+        }
+        self.output.push(value);
+    }
+
+    fn rel_ip(&self, offset: DataType) -> Result<usize> {
+        self.check_addr((self.rel_base as DataType).saturating_add(offset))
+    }
+
+    pub(crate) fn check_addr(&self, addr: DataType) -> Result<usize> {
+        if addr < 0 {
+            return Err(self.error(|ip, opcode| IntcodeError::NegativeAddress {
+                ip,
+                opcode,
+                addr,
+            }));
+        }
+        if addr as u64 > self.max_addr as u64 {
+            let max_addr = self.max_addr;
+            return Err(self.error(|ip, opcode| IntcodeError::MemoryLimit {
+                ip,
+                opcode,
+                addr,
+                max_addr,
+            }));
+        }
+        Ok(addr as usize)
+    }
+
+    /// Set `Status::Interrupted` if some limit is exceeded
+    fn interrupted(&mut self, count: u64, deadline: Option<Instant>) -> bool {
+        match self.limits.check(self.steps, count, deadline) {
+            Some(interrupt) => {
+                self.status = Status::Interrupted(interrupt);
+                self.log.println(format!(
+                    "=> Interrupted: {:?}. Steps: {}",
+                    interrupt, self.steps
+                ));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fault of the instruction at current ip
+    fn error<F>(&self, f: F) -> anyhow::Error
+    where
+        F: FnOnce(usize, DataType) -> IntcodeError,
+    {
+        f(self.ip, self.mem.get(self.ip)).into()
+    }
+
+    /// Fault of running the halted program, reported at the instruction that halted it
+    fn halted_error(&self) -> anyhow::Error {
+        let ip = self.halt_ip.unwrap_or(self.ip);
+        IntcodeError::Halted {
+            ip,
+            opcode: self.mem.get(ip),
+        }
+        .into()
+    }
+
+    /// Apply checked or wrapping version of the operation according to the arithmetic mode
+    fn arith(
+        &self,
+        a: DataType,
+        b: DataType,
+        checked: fn(DataType, DataType) -> Option<DataType>,
+        wrapping: fn(DataType, DataType) -> DataType,
+    ) -> Result<DataType> {
+        match self.arithmetic {
+            Arithmetic::Checked => checked(a, b)
+                .ok_or_else(|| self.error(|ip, opcode| IntcodeError::Overflow { ip, opcode })),
+            Arithmetic::Wrapping => Ok(wrapping(a, b)),
+        }
+    }
+
+    /// Command at ip and number of its parameters
+    fn decode(&mut self) -> std::result::Result<(Command, usize), IntcodeError> {
+        let ip = self.ip;
+        if let Some(decoded) = self.cache.as_mut().and_then(|cache| cache.get(ip)) {
+            return Ok(decoded);
+        }
+
+        let (cmd, params_count) = self.isa.parse(self.ip, self.mem.get(self.ip))?;
+        if let Some(cache) = &mut self.cache {
+            cache.insert(self.ip, cmd, params_count);
+        }
+
+        Ok((cmd, params_count))
+    }
+
+    fn write_mem(&mut self, addr: usize, value: DataType) {
+        self.mem.set(addr, value);
+        if let Some(cache) = &mut self.cache {
+            cache.invalidate(addr);
+        }
+    }
+
+    fn clear_cache(&mut self) {
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
     }
 
     /// Returns false if execution should be stopped or paused
     fn eval_cmd(&mut self) -> Result<bool> {
         ensure!(self.is_running(), "Program is not running.");
 
-        let (cmd, params_count) =
-            Command::parse(self.prog[self.ip]).with_context(|| format!("ip={}", self.ip))?;
+        let (cmd, params_count) = self.decode()?;
+
+        if self.log.is_active() || self.tracer.is_some() {
+            self.event = Some(TraceEvent::new(
+                self.steps,
+                self.ip,
+                self.mem.get(self.ip),
+                cmd.mnemonic(),
+                self.rel_base,
+            ));
+        }
+
+        if let Some(journal) = &mut self.journal {
+            if journal.need_checkpoint(self.steps) {
+                let snapshot = self.snapshot();
+                if let Some(journal) = &mut self.journal {
+                    journal.add_checkpoint(snapshot);
+                }
+            }
+
+            self.journal_entry = Some(JournalEntry::new(
+                self.steps,
+                self.ip,
+                self.rel_base,
+                self.status.clone(),
+                self.mem.size(),
+                self.output_taken + self.output.len(),
+            ));
+        }
 
-        self.log.print(format!("[{:4}] ", self.ip));
+        let mut next_ip = self.ip + params_count + 1;
 
         match cmd {
             Command::Add(m1, m2, m3) => {
-                self.log.print("ADD");
                 let v1 = self.get_param_value(1, m1)?;
                 let v2 = self.get_param_value(2, m2)?;
 
-                self.set_param_value(3, m3, v1 + v2)?;
+                let value = self.arith(v1, v2, DataType::checked_add, DataType::wrapping_add)?;
+                self.set_param_value(3, m3, value)?;
             }
             Command::Mul(m1, m2, m3) => {
-                self.log.print("MUL");
                 let v1 = self.get_param_value(1, m1)?;
                 let v2 = self.get_param_value(2, m2)?;
 
-                self.set_param_value(3, m3, v1 * v2)?;
+                let value = self.arith(v1, v2, DataType::checked_mul, DataType::wrapping_mul)?;
+                self.set_param_value(3, m3, value)?;
             }
             Command::Read(m1) => {
-                self.log.print("GET");
                 if self.input.is_empty() {
                     self.status = Status::WaitForInput;
-                    self.log.println("    Waiting for input");
+                    if let Some(event) = &mut self.event {
+                        event.wait_for_input = true;
+                    }
+                    self.emit_event()?;
+                    self.journal_entry = None;
                     return Ok(false);
                 }
 
-                let value = self.input.remove(0);
-
-                self.set_param_value(1, m1, value)?;
+                if let Some(value) = self.take_input() {
+                    self.set_param_value(1, m1, value)?;
+                }
             }
             Command::Write(m1) => {
-                self.log.print("SET");
                 let value = self.get_param_value(1, m1)?;
-                self.output.push(value);
+                self.push_output(value);
             }
             Command::JumpIfTrue(m1, m2) => {
-                self.log.print("JIT");
                 let v1 = self.get_param_value(1, m1)?;
 
                 if v1 != 0 {
                     let v2 = self.get_param_value(2, m2)?;
-                    self.ip = v2 as usize;
-                    self.log.println("");
-                    return Ok(true);
+                    next_ip = self.check_addr(v2)?;
                 }
             }
             Command::JumpIfFalse(m1, m2) => {
-                self.log.print("JIF");
                 let v1 = self.get_param_value(1, m1)?;
 
                 if v1 == 0 {
                     let v2 = self.get_param_value(2, m2)?;
-                    self.ip = v2 as usize;
-                    self.log.println("");
-                    return Ok(true);
+                    next_ip = self.check_addr(v2)?;
                 }
             }
             Command::LessThan(m1, m2, m3) => {
-                self.log.print(" LT");
                 let v1 = self.get_param_value(1, m1)?;
                 let v2 = self.get_param_value(2, m2)?;
 
                 self.set_param_value(3, m3, if v1 < v2 { 1 } else { 0 })?;
             }
             Command::Equals(m1, m2, m3) => {
-                self.log.print(" EQ");
                 let v1 = self.get_param_value(1, m1)?;
                 let v2 = self.get_param_value(2, m2)?;
 
                 self.set_param_value(3, m3, if v1 == v2 { 1 } else { 0 })?;
             }
             Command::AdjustRelBase(m1) => {
-                self.log.print("ARB");
                 let v1 = self.get_param_value(1, m1)?;
 
-                self.rel_base = self.rel_ip(v1);
-                self.log.print(format!("->{}", self.rel_base));
+                self.rel_base = self.rel_ip(v1)?;
+                if let Some(event) = &mut self.event {
+                    event.new_rel_base = Some(self.rel_base);
+                }
             }
             Command::Exit => {
-                self.log.print("EXIT");
                 self.status = Status::Halted;
+                self.halt_ip = Some(self.ip);
+            }
+            Command::Ext(ext) => {
+                let handler = match self.isa.handler(ext.opcode) {
+                    Some(handler) => handler,
+                    None => {
+                        return Err(
+                            self.error(|ip, opcode| IntcodeError::InvalidOpcode { ip, opcode })
+                        )
+                    }
+                };
+
+                let mut ctx = OpContext::new(self, ext, next_ip);
+                handler(&mut ctx)?;
+                let (ip, halted) = ctx.result();
+
+                next_ip = ip;
+                if halted {
+                    self.status = Status::Halted;
+                    self.halt_ip = Some(self.ip);
+                }
             }
         }
-        self.log.println("");
 
-        self.ip += params_count + 1;
+        self.emit_event()?;
+
+        if let (Some(journal), Some(entry)) = (&mut self.journal, self.journal_entry.take()) {
+            journal.push(entry);
+        }
+
+        if let Some(profile) = &mut self.profile {
+            profile.instruction(self.ip, cmd, self.ip + params_count + 1, next_ip);
+        }
+
+        self.ip = next_ip;
+        self.steps += 1;
 
         Ok(self.status == Status::Running)
     }
 
-    fn check_and_extend(&mut self, ip: usize) {
-        if ip >= self.prog.len() {
-            // self.log.println(format!(
-            //     "    Extending program from {} to {} elements.",
-            //     self.prog.len(),
-            //     ip + 1
-            // ));
-            self.prog.resize(ip + 1, 0);
+    fn emit_event(&mut self) -> Result<()> {
+        if let Some(event) = self.event.take() {
+            self.log.println(event.to_string());
+
+            if let Some(tracer) = &self.tracer {
+                tracer
+                    .lock()
+                    .map_err(|_| anyhow!("Trace sink is poisoned"))?
+                    .event(&event)?;
+            }
         }
-    }
 
-    fn get_param_value(&mut self, param_offset: usize, mode: ParamMode) -> Result<DataType> {
-        let ip = self.ip + param_offset;
-        // self.log.println(format!("    get_param_value({}, {:?}): ip={} prog={} ip_off={}", param_offset, mode, self.ip, self.prog.len(), ip));
+        Ok(())
+    }
 
-        self.check_and_extend(ip);
+    fn trace_param(
+        &mut self,
+        mode: ParamMode,
+        raw: DataType,
+        addr: Option<usize>,
+        value: DataType,
+        write: bool,
+    ) {
+        if let Some(event) = &mut self.event {
+            event.params.push(TraceParam {
+                mode,
+                raw,
+                addr,
+                value,
+                write,
+            });
+        }
+    }
 
-        let value = match mode {
-            ParamMode::Position => {
-                let val_ip = self.prog[ip] as usize;
-                self.check_and_extend(val_ip);
-                self.log
-                    .print(format!(" p[{}]->{}", val_ip, self.prog[val_ip]));
-                self.prog[val_ip]
-            }
-            ParamMode::Immediate => {
-                self.log.print(format!(" i[{}]", self.prog[ip]));
-                self.prog[ip]
-            }
+    /// Memory address the parameter points to. Value for parameters not pointing to memory.
+    fn resolve_param(&self, mode: ParamMode, raw: DataType) -> Result<ModeTarget> {
+        Ok(match mode {
+            ParamMode::Position => ModeTarget::Addr(raw),
+            ParamMode::Immediate => ModeTarget::Value(raw),
             ParamMode::Relative => {
-                let val_ip = self.rel_ip(self.prog[ip]);
-                self.check_and_extend(val_ip);
-                self.log.print(format!(
-                    " r[{}+{}]->{}",
-                    self.prog[ip], self.rel_base, self.prog[val_ip]
-                ));
-                self.prog[val_ip]
+                ModeTarget::Addr((self.rel_base as DataType).saturating_add(raw))
+            }
+            ParamMode::Custom(mode) => match self.isa.resolver(mode as DataType) {
+                Some(resolver) => resolver(self, raw)?,
+                None => {
+                    return Err(self.error(|ip, opcode| IntcodeError::InvalidParamMode {
+                        ip,
+                        opcode,
+                        mode: mode as DataType,
+                    }))
+                }
+            },
+        })
+    }
+
+    pub(crate) fn get_param_value(
+        &mut self,
+        param_offset: usize,
+        mode: ParamMode,
+    ) -> Result<DataType> {
+        let raw = self.mem.get(self.ip + param_offset);
+        let (addr, value) = match self.resolve_param(mode, raw)? {
+            ModeTarget::Addr(addr) => {
+                let addr = self.check_addr(addr)?;
+                (Some(addr), self.mem.get(addr))
             }
+            ModeTarget::Value(value) => (None, value),
         };
 
+        self.trace_param(mode, raw, addr, value, false);
+
+        if let (Some(profile), Some(addr)) = (&mut self.profile, addr) {
+            profile.read(addr);
+        }
+
         Ok(value)
     }
 
-    fn set_param_value(
+    pub(crate) fn set_param_value(
         &mut self,
         param_offset: usize,
         mode: ParamMode,
         value: DataType,
     ) -> Result<()> {
-        ensure!(
-            mode == ParamMode::Position || mode == ParamMode::Relative,
-            "Wrong destination parameter. Expected Position or Relative but was {:?}.",
-            mode
-        );
+        let raw = self.mem.get(self.ip + param_offset);
+        let addr = match self.resolve_param(mode, raw)? {
+            ModeTarget::Addr(addr) => self.check_addr(addr)?,
+            ModeTarget::Value(_) => {
+                return Err(self.error(|ip, opcode| IntcodeError::WriteToImmediate { ip, opcode }))
+            }
+        };
 
-        let ip = self.ip + param_offset;
+        if let Some(entry) = &mut self.journal_entry {
+            entry.writes.push((addr, self.mem.get(addr)));
+        }
 
-        self.check_and_extend(ip);
+        self.write_mem(addr, value);
 
-        let val_ip = if mode == ParamMode::Relative {
-            self.log
-                .print(format!(" r[{}+{}]<-", self.prog[ip], self.rel_base));
-            self.rel_ip(self.prog[ip])
-        } else {
-            self.log.print(format!(" p[{}]<-", self.prog[ip]));
-            self.prog[ip] as usize
-        };
-        self.log.print(format!("{}", value));
+        self.trace_param(mode, raw, Some(addr), value, true);
+
+        if let Some(profile) = &mut self.profile {
+            profile.write(addr, value, mode);
+        }
 
-        self.check_and_extend(val_ip as usize);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+
+    #[test]
+    fn test_phase() -> Result<()> {
+        // Day 7 example. Max thruster signal 43210 for phases 4,3,2,1,0.
+        let log = Log::new(false);
+        let prog = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+        let mut signal = 0;
+
+        for phase in &[4, 3, 2, 1, 0] {
+            let mut comp = IntcodeComp::with_phase(Vec::new(), *phase, &log);
+            comp.load_prog(prog)?;
+            signal = comp.run_with_input(signal)?[0];
+            assert!(comp.is_halted());
+        }
+
+        assert_eq!(signal, 43210);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_until_output() -> Result<()> {
+        // Day 9 quine
+        let log = Log::new(false);
+        let prog = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut comp = IntcodeComp::from_prog(prog, &log)?;
+
+        let mut output = Vec::new();
+        while let Some(value) = comp.run_until_output()? {
+            assert_eq!(comp.output(), &[] as &[DataType]);
+            output.push(value);
+        }
+
+        assert!(comp.is_halted());
+        assert_eq!(to_prog_str(&output), prog);
+        assert_eq!(comp.run_until_output()?, None);
+
+        // Waiting for input
+        let mut comp = IntcodeComp::new(vec![3, 5, 4, 5, 99, 0], &log);
+        assert_eq!(comp.run_until_output()?, None);
+        assert_eq!(comp.get_status(), Status::WaitForInput);
+        comp.add_input(7);
+        assert_eq!(comp.run_until_output()?, Some(7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_arithmetic() -> Result<()> {
+        let log = Log::new(false);
+        // ADD #MAX, #1, [7]; SET [7]
+        let prog = vec![1101, DataType::MAX, 1, 7, 4, 7, 99, 0];
+
+        let mut comp = IntcodeComp::new(prog.clone(), &log);
+        assert_eq!(comp.get_arithmetic(), Arithmetic::Wrapping);
+        comp.set_arithmetic(Arithmetic::Checked);
+        let err = comp.exec().unwrap_err().downcast::<IntcodeError>()?;
+        assert_eq!(
+            err,
+            IntcodeError::Overflow {
+                ip: 0,
+                opcode: 1101
+            }
+        );
+        assert_eq!(comp.get_mem(7), 0);
 
-        self.prog[val_ip as usize] = value;
+        let mut comp = IntcodeComp::new(prog, &log);
+        comp.exec()?;
+        assert_eq!(comp.get_output(), vec![DataType::MIN]);
 
+        let mut comp = IntcodeComp::new(vec![1102, DataType::MIN, -1, 5, 99, 0], &log);
+        comp.set_arithmetic(Arithmetic::Wrapping);
+        comp.exec()?;
+        assert_eq!(comp.get_mem(5), DataType::MIN);
         Ok(())
     }
 }
@@ -0,0 +1,250 @@
+use crate::intcode_comp::*;
+use crate::intcode_fuzz::*;
+use crate::intcode_loader::parse_prog;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedStop {
+    #[default]
+    Halted,
+    WaitForInput,
+    Fault,
+}
+
+/// Program with its input and expected results. Fields left out are not checked.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Case {
+    pub name: String,
+    /// Comma separated program
+    pub prog: String,
+    #[serde(default)]
+    pub input: Vec<DataType>,
+    pub output: Option<Vec<DataType>>,
+    /// Whole memory after the run. Trailing zeros are ignored.
+    pub memory: Option<Vec<DataType>>,
+    #[serde(default)]
+    pub stop: ExpectedStop,
+    /// Name of the `IntcodeError` variant
+    pub fault: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Failure {
+    pub case: String,
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.case, self.problems.join("; "))
+    }
+}
+
+/// Cases from the JSON array file
+pub fn load_cases<P: AsRef<Path>>(path: P) -> Result<Vec<Case>> {
+    let path = path.as_ref();
+    let data = fs::read_to_string(path)?;
+    serde_json::from_str(&data).map_err(|err| anyhow!("{}: {}", path.display(), err))
+}
+
+/// Cases from all `.json` files of the directory in file name order
+pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<Case>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut cases = Vec::new();
+    for path in paths {
+        cases.extend(load_cases(path)?);
+    }
+    Ok(cases)
+}
+
+/// Limits used to run conformance cases
+pub fn default_config() -> FuzzConfig {
+    FuzzConfig {
+        max_steps: 1_000_000,
+        max_addr: crate::intcode_memory::DEFAULT_MAX_ADDR,
+        ..FuzzConfig::default()
+    }
+}
+
+/// Run every case on `vm` (e.g. `comp_run` or `reference_run`) and return failed ones
+pub fn check<F>(cases: &[Case], config: &FuzzConfig, vm: F) -> Result<Vec<Failure>>
+where
+    F: Fn(&[DataType], &[DataType], &FuzzConfig) -> Outcome,
+{
+    let mut failures = Vec::new();
+
+    for case in cases {
+        let prog = parse_prog(&case.prog).map_err(|err| anyhow!("{}: {}", case.name, err))?;
+        let outcome = vm(&prog, &case.input, config);
+        let problems = compare(case, &outcome);
+
+        if !problems.is_empty() {
+            failures.push(Failure {
+                case: case.name.clone(),
+                problems,
+            });
+        }
+    }
+
+    Ok(failures)
+}
+
+fn compare(case: &Case, outcome: &Outcome) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let stop_matches = match (&case.stop, &outcome.stop) {
+        (ExpectedStop::Halted, Stop::Halted) | (ExpectedStop::WaitForInput, Stop::WaitForInput) => {
+            true
+        }
+        (ExpectedStop::Fault, Stop::Fault(fault)) => case
+            .fault
+            .as_ref()
+            .is_none_or(|name| format!("{:?}", fault).starts_with(&format!("{} ", name))),
+        _ => false,
+    };
+    if !stop_matches {
+        problems.push(format!(
+            "expected stop {:?} {}, got {:?}",
+            case.stop,
+            case.fault.as_deref().unwrap_or(""),
+            outcome.stop
+        ));
+    }
+
+    if let Some(output) = &case.output {
+        if *output != outcome.output {
+            problems.push(format!(
+                "expected output {:?}, got {:?}",
+                output, outcome.output
+            ));
+        }
+    }
+
+    if let Some(memory) = &case.memory {
+        let mut memory = memory.clone();
+        while memory.last() == Some(&0) {
+            memory.pop();
+        }
+        if memory != outcome.memory {
+            problems.push(format!(
+                "expected memory {:?}, got {:?}",
+                memory, outcome.memory
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_bigint::*;
+    use num_bigint::BigInt;
+    use num_traits::ToPrimitive;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/conformance");
+
+    fn assert_passes<F>(vm: F) -> Result<()>
+    where
+        F: Fn(&[DataType], &[DataType], &FuzzConfig) -> Outcome,
+    {
+        assert_cases_pass(&load_dir(FIXTURES)?, vm)
+    }
+
+    fn assert_cases_pass<F>(cases: &[Case], vm: F) -> Result<()>
+    where
+        F: Fn(&[DataType], &[DataType], &FuzzConfig) -> Outcome,
+    {
+        assert!(cases.len() > 50);
+
+        let failures = check(cases, &default_config(), vm)?;
+        let report: Vec<String> = failures.iter().map(|f| f.to_string()).collect();
+        assert!(failures.is_empty(), "\n{}", report.join("\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_comp() -> Result<()> {
+        assert_passes(comp_run)?;
+        assert_passes(
+            |prog: &[DataType], input: &[DataType], config: &FuzzConfig| {
+                let config = FuzzConfig {
+                    decode_cache: false,
+                    ..config.clone()
+                };
+                comp_run(prog, input, &config)
+            },
+        )
+    }
+
+    #[test]
+    fn test_reference() -> Result<()> {
+        assert_passes(reference_run)
+    }
+
+    fn bigint_run(prog: &[DataType], input: &[DataType], config: &FuzzConfig) -> Outcome {
+        let mut comp = BigIntcodeComp::new(prog.iter().map(|v| BigInt::from(*v)).collect());
+        comp.set_max_addr(config.max_addr);
+        comp.set_instruction_limit(Some(config.max_steps));
+        input.iter().for_each(|v| comp.add_input(*v));
+
+        let result = comp.run();
+        let small = |values: &[BigInt]| -> Vec<DataType> {
+            values.iter().map(|v| v.to_i64().unwrap()).collect()
+        };
+
+        Outcome {
+            stop: crate::intcode_fuzz::stop(result, comp.get_status()),
+            ip: comp.get_ip(),
+            rel_base: comp.get_rel_base(),
+            steps: comp.get_steps(),
+            output: small(comp.output()),
+            memory: crate::intcode_fuzz::trimmed(small(comp.memory())),
+        }
+    }
+
+    #[test]
+    fn test_bigint() -> Result<()> {
+        // Arbitrary precision doesn't overflow
+        let cases: Vec<Case> = load_dir(FIXTURES)?
+            .into_iter()
+            .filter(|case| case.fault.as_deref() != Some("Overflow"))
+            .collect();
+        assert_cases_pass(&cases, bigint_run)
+    }
+
+    #[test]
+    fn test_failures() -> Result<()> {
+        let cases: Vec<Case> = serde_json::from_str(
+            r#"[
+                { "name": "wrong output", "prog": "104,1,99", "output": [2] },
+                { "name": "wrong fault", "prog": "42", "stop": "fault", "fault": "NegativeAddress" },
+                { "name": "not halted", "prog": "3,0,99" }
+            ]"#,
+        )?;
+
+        let failures = check(&cases, &default_config(), comp_run)?;
+        assert_eq!(failures.len(), 3);
+        assert_eq!(
+            failures[0].to_string(),
+            "wrong output: expected output [2], got [1]"
+        );
+        assert!(failures[1].problems[0].contains("InvalidOpcode"));
+        assert!(failures[2].problems[0].contains("WaitForInput"));
+        Ok(())
+    }
+}
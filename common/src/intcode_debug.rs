@@ -0,0 +1,286 @@
+use crate::intcode_comp::*;
+use crate::intcode_limits::*;
+use crate::intcode_trace::*;
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    fn matches(self, write: bool) -> bool {
+        match self {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StopReason {
+    /// Single instruction was executed
+    Step,
+    Breakpoint(usize),
+    Watchpoint {
+        ip: usize,
+        addr: usize,
+        value: DataType,
+        write: bool,
+    },
+    WaitForInput,
+    Halted,
+    Interrupted(Interrupt),
+}
+
+/// Step debugger. Breakpoints stop execution before instruction at given ip is executed,
+/// watchpoints stop it right after instruction that accessed watched address.
+/// Tracer of the computer keeps receiving events while it is debugged.
+pub struct Debugger<'l> {
+    comp: IntcodeComp<'l>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, WatchKind>,
+    events: Arc<Mutex<RingBufferSink>>,
+    tracer: Option<SharedTraceSink>,
+}
+
+impl<'l> Debugger<'l> {
+    pub fn new(mut comp: IntcodeComp<'l>) -> Self {
+        let events = Arc::new(Mutex::new(RingBufferSink::new(1)));
+        let tracer = comp.tracer().cloned();
+
+        match &tracer {
+            Some(tracer) => comp.set_tracer(Arc::new(Mutex::new(TeeSink::new(
+                events.clone(),
+                tracer.clone(),
+            )))),
+            None => comp.set_tracer(events.clone()),
+        }
+
+        Self {
+            comp,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            events,
+            tracer,
+        }
+    }
+
+    pub fn comp(&self) -> &IntcodeComp<'l> {
+        &self.comp
+    }
+
+    pub fn comp_mut(&mut self) -> &mut IntcodeComp<'l> {
+        &mut self.comp
+    }
+
+    /// Computer with its own tracer back
+    pub fn into_comp(mut self) -> IntcodeComp<'l> {
+        match self.tracer {
+            Some(tracer) => self.comp.set_tracer(tracer),
+            None => self.comp.clear_tracer(),
+        }
+        self.comp
+    }
+
+    pub fn add_breakpoint(&mut self, ip: usize) {
+        self.breakpoints.insert(ip);
+    }
+
+    pub fn remove_breakpoint(&mut self, ip: usize) -> bool {
+        self.breakpoints.remove(&ip)
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    pub fn add_watchpoint(&mut self, addr: usize, kind: WatchKind) {
+        self.watchpoints.insert(addr, kind);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: usize) -> bool {
+        self.watchpoints.remove(&addr).is_some()
+    }
+
+    pub fn watchpoints(&self) -> &BTreeMap<usize, WatchKind> {
+        &self.watchpoints
+    }
+
+    /// Last executed instruction
+    pub fn last_event(&self) -> Result<Option<TraceEvent>> {
+        Ok(self.events()?.events().back().cloned())
+    }
+
+    /// Execute single instruction
+    pub fn step(&mut self) -> Result<StopReason> {
+        if self.comp.is_halted() {
+            return Ok(StopReason::Halted);
+        }
+
+        self.events()?.clear();
+
+        self.comp.step()?;
+
+        if let Some(reason) = self.check_watchpoints()? {
+            return Ok(reason);
+        }
+
+        Ok(match self.comp.get_status() {
+            Status::Halted => StopReason::Halted,
+            Status::WaitForInput => StopReason::WaitForInput,
+            Status::Running => StopReason::Step,
+            Status::Interrupted(interrupt) => StopReason::Interrupted(interrupt),
+        })
+    }
+
+    /// Run until breakpoint, watchpoint, halt or lack of input
+    pub fn cont(&mut self) -> Result<StopReason> {
+        loop {
+            let reason = self.step()?;
+
+            if reason != StopReason::Step {
+                return Ok(reason);
+            }
+
+            let ip = self.comp.get_ip();
+            if self.breakpoints.contains(&ip) {
+                return Ok(StopReason::Breakpoint(ip));
+            }
+        }
+    }
+
+    fn events(&self) -> Result<MutexGuard<'_, RingBufferSink>> {
+        self.events
+            .lock()
+            .map_err(|_| anyhow!("Trace sink is poisoned"))
+    }
+
+    fn check_watchpoints(&self) -> Result<Option<StopReason>> {
+        let events = self.events()?;
+        let event = match events.events().back() {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+
+        Ok(event.params.iter().find_map(|param| {
+            let addr = param.addr?;
+            let kind = self.watchpoints.get(&addr)?;
+
+            if kind.matches(param.write) {
+                Some(StopReason::Watchpoint {
+                    ip: event.ip,
+                    addr,
+                    value: param.value,
+                    write: param.write,
+                })
+            } else {
+                None
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+
+    // Counts down from input value to zero outputting every value
+    const PROG: [DataType; 14] = [3, 13, 4, 13, 1001, 13, -1, 13, 1005, 13, 2, 99, 0, 0];
+
+    #[test]
+    fn test_breakpoint() -> Result<()> {
+        let log = Log::new(false);
+        let mut dbg = Debugger::new(IntcodeComp::new(PROG.to_vec(), &log));
+
+        dbg.add_breakpoint(8);
+
+        assert_eq!(dbg.cont()?, StopReason::WaitForInput);
+        dbg.comp_mut().add_input(2);
+        assert_eq!(dbg.cont()?, StopReason::Breakpoint(8));
+        assert_eq!(dbg.comp().output(), &[2]);
+        assert_eq!(dbg.comp().get_mem(13), 1);
+
+        dbg.comp_mut().set_mem(13, 5);
+        assert_eq!(dbg.step()?, StopReason::Step);
+        assert_eq!(dbg.comp().get_ip(), 2);
+        assert_eq!(dbg.cont()?, StopReason::Breakpoint(8));
+        assert_eq!(dbg.comp().output(), &[2, 5]);
+
+        // Resumed from the breakpoint, hit again on the next loop
+        assert_eq!(dbg.cont()?, StopReason::Breakpoint(8));
+        assert_eq!(dbg.comp().output(), &[2, 5, 4]);
+
+        dbg.remove_breakpoint(8);
+        assert_eq!(dbg.cont()?, StopReason::Halted);
+        assert_eq!(dbg.comp().output(), &[2, 5, 4, 3, 2, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tracer() -> Result<()> {
+        let log = Log::new(false);
+        let ring = Arc::new(Mutex::new(RingBufferSink::new(100)));
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+        comp.set_tracer(ring.clone());
+        comp.add_input(1);
+
+        let mut dbg = Debugger::new(comp);
+        dbg.add_breakpoint(8);
+        assert_eq!(dbg.cont()?, StopReason::Breakpoint(8));
+        assert_eq!(dbg.last_event()?.unwrap().mnemonic, "ADD");
+        assert_eq!(ring.lock().unwrap().events().len(), 3);
+
+        let mut comp = dbg.into_comp();
+        comp.exec()?;
+        assert_eq!(ring.lock().unwrap().events().len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watchpoint() -> Result<()> {
+        let log = Log::new(false);
+        let mut dbg = Debugger::new(IntcodeComp::new(PROG.to_vec(), &log));
+
+        dbg.comp_mut().add_input(2);
+        dbg.add_watchpoint(13, WatchKind::Write);
+
+        assert_eq!(
+            dbg.cont()?,
+            StopReason::Watchpoint {
+                ip: 0,
+                addr: 13,
+                value: 2,
+                write: true
+            }
+        );
+        assert_eq!(
+            dbg.cont()?,
+            StopReason::Watchpoint {
+                ip: 4,
+                addr: 13,
+                value: 1,
+                write: true
+            }
+        );
+
+        dbg.add_watchpoint(13, WatchKind::Read);
+        assert_eq!(
+            dbg.cont()?,
+            StopReason::Watchpoint {
+                ip: 8,
+                addr: 13,
+                value: 1,
+                write: false
+            }
+        );
+        assert_eq!(dbg.last_event()?.unwrap().mnemonic, "JIT");
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::intcode_comp::*;
+
+/// Instructions decoded by address. Only the opcode cell is decoded, parameters are
+/// read from memory on execution, so writing an opcode cell drops its entry and
+/// self-modifying programs are executed the same way as without the cache.
+#[derive(Debug, Default, Clone)]
+pub struct DecodeCache {
+    entries: Vec<Option<(Command, usize)>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl DecodeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Command and number of its parameters
+    pub fn get(&mut self, ip: usize) -> Option<(Command, usize)> {
+        let entry = self.entries.get(ip).cloned().flatten();
+
+        if entry.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+
+        entry
+    }
+
+    pub fn insert(&mut self, ip: usize, cmd: Command, params_count: usize) {
+        if ip >= self.entries.len() {
+            self.entries.resize(ip + 1, None);
+        }
+        self.entries[ip] = Some((cmd, params_count));
+    }
+
+    /// Drop instruction decoded at the address
+    pub fn invalidate(&mut self, addr: usize) {
+        if let Some(entry) = self.entries.get_mut(addr) {
+            *entry = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Lookups that found decoded instruction
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+    use crate::log::*;
+    use anyhow::Result;
+
+    // Second pass runs patched opcode: MUL instead of ADD, third one patched operand 5
+    const PROG: &str = "
+        loop: ADD  [x], #3, [x]
+              SET  [x]
+              EQ   [pass], #2, [t]
+              JIT  [t], #end
+              ADD  [pass], #1, [pass]
+              EQ   [pass], #1, [t]
+              JIT  [t], #op
+              ADD  #5, #0, [loop+2]
+              JIT  #1, #loop
+        op:   ADD  #1002, #0, [loop]
+              JIT  #1, #loop
+        end:  EXIT
+        x:    db 1
+        pass: db 0
+        t:    db 0
+    ";
+
+    fn run(cache: bool) -> Result<Vec<DataType>> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(assemble(PROG)?, &log);
+        comp.set_decode_cache(cache);
+        comp.exec()?;
+        Ok(comp.get_output())
+    }
+
+    #[test]
+    fn test_self_modifying() -> Result<()> {
+        // 1 + 3, 4 * 3, 12 * 5
+        assert_eq!(run(true)?, vec![4, 12, 60]);
+        assert_eq!(run(false)?, vec![4, 12, 60]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_host_write() -> Result<()> {
+        let log = Log::new(false);
+        // GET [9]; SET [9]; JIT #1, #0
+        let mut comp = IntcodeComp::new(vec![3, 9, 4, 9, 1105, 1, 0, 99, 0, 0], &log);
+
+        comp.add_input(1);
+        comp.run()?;
+        assert_eq!(comp.get_output(), vec![1]);
+
+        // SET becomes EXIT
+        comp.set_mem(2, 99);
+        assert!(comp.run_with_input(2)?.is_empty());
+        assert!(comp.is_halted());
+
+        let mut cache = DecodeCache::new();
+        cache.insert(4, Command::Exit, 2);
+        cache.invalidate(5);
+        assert!(cache.get(4).is_some());
+        cache.invalidate(4);
+        assert!(cache.get(4).is_none());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        Ok(())
+    }
+}
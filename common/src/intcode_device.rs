@@ -0,0 +1,278 @@
+use crate::intcode_comp::*;
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Read, Stdin, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// I/O port of the `IntcodeComp`. Called synchronously by `IntcodeComp::run_device`.
+pub trait Device {
+    /// Value for the input instruction. None pauses the program with `Status::WaitForInput`.
+    fn input(&mut self) -> Result<Option<DataType>>;
+
+    fn output(&mut self, value: DataType) -> Result<()>;
+}
+
+/// Reads values from the queue and collects output
+#[derive(Debug, Default, Clone)]
+pub struct VecDevice {
+    input: VecDeque<DataType>,
+    output: Vec<DataType>,
+}
+
+impl VecDevice {
+    pub fn new(input: Vec<DataType>) -> Self {
+        Self {
+            input: input.into(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn add_input(&mut self, value: DataType) {
+        self.input.push_back(value);
+    }
+
+    pub fn output(&self) -> &[DataType] {
+        &self.output
+    }
+
+    pub fn get_output(&mut self) -> Vec<DataType> {
+        std::mem::take(&mut self.output)
+    }
+}
+
+impl Device for VecDevice {
+    fn input(&mut self) -> Result<Option<DataType>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn output(&mut self, value: DataType) -> Result<()> {
+        self.output.push(value);
+        Ok(())
+    }
+}
+
+/// Device built from two closures
+pub struct FnDevice<I, O> {
+    input: I,
+    output: O,
+}
+
+impl<I, O> FnDevice<I, O>
+where
+    I: FnMut() -> Option<DataType>,
+    O: FnMut(DataType),
+{
+    pub fn new(input: I, output: O) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<I, O> Device for FnDevice<I, O>
+where
+    I: FnMut() -> Option<DataType>,
+    O: FnMut(DataType),
+{
+    fn input(&mut self) -> Result<Option<DataType>> {
+        Ok((self.input)())
+    }
+
+    fn output(&mut self, value: DataType) -> Result<()> {
+        (self.output)(value);
+        Ok(())
+    }
+}
+
+/// Input blocks until a value is received. Disconnected input channel pauses the program.
+pub struct ChannelDevice {
+    input: Receiver<DataType>,
+    output: Sender<DataType>,
+}
+
+impl ChannelDevice {
+    pub fn new(input: Receiver<DataType>, output: Sender<DataType>) -> Self {
+        Self { input, output }
+    }
+}
+
+impl Device for ChannelDevice {
+    fn input(&mut self) -> Result<Option<DataType>> {
+        Ok(self.input.recv().ok())
+    }
+
+    fn output(&mut self, value: DataType) -> Result<()> {
+        self.output
+            .send(value)
+            .map_err(|_| anyhow!("Output channel is closed. Value: {}", value))
+    }
+}
+
+/// ASCII text stream. Input is read byte by byte, end of stream pauses the program.
+/// Non-ASCII output values are written as numbers on separate lines.
+pub struct TextDevice<R: Read, W: Write> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: Read, W: Write> TextDevice<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl TextDevice<Stdin, Stdout> {
+    pub fn stdio() -> Self {
+        Self::new(io::stdin(), io::stdout())
+    }
+}
+
+impl<R: Read, W: Write> Device for TextDevice<R, W> {
+    fn input(&mut self) -> Result<Option<DataType>> {
+        self.writer.flush()?;
+
+        let mut buf = [0u8; 1];
+        if self.reader.read(&mut buf)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(buf[0] as DataType))
+    }
+
+    fn output(&mut self, value: DataType) -> Result<()> {
+        if (0..128).contains(&value) {
+            self.writer.write_all(&[value as u8])?;
+        } else {
+            writeln!(self.writer, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Numeric values from stdin (one per line) to stdout
+#[derive(Default)]
+pub struct StdioDevice;
+
+impl StdioDevice {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Device for StdioDevice {
+    fn input(&mut self) -> Result<Option<DataType>> {
+        print!("Input: ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(line.trim().parse()?))
+    }
+
+    fn output(&mut self, value: DataType) -> Result<()> {
+        println!("{}", value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+    use std::cell::Cell;
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    // Outputs doubled input values until zero is read
+    const PROG: [DataType; 16] = [
+        3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+    ];
+
+    #[test]
+    fn test_vec() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+        let mut device = VecDevice::new(vec![1, 2]);
+
+        comp.run_device(&mut device)?;
+        assert_eq!(comp.get_status(), Status::WaitForInput);
+        assert_eq!(device.get_output(), vec![2, 4]);
+
+        device.add_input(5);
+        device.add_input(0);
+        comp.run_device(&mut device)?;
+        assert_eq!(comp.get_status(), Status::Halted);
+        assert_eq!(device.output(), &[10]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fn() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+        let mut input = vec![0, 3];
+        let mut output = Vec::new();
+
+        // Next input depends on the previous output
+        let last = Cell::new(None);
+        comp.run_device(&mut FnDevice::new(
+            || {
+                last.take()
+                    .filter(|v| *v < 30)
+                    .map(|v: DataType| v - 1)
+                    .or_else(|| input.pop())
+            },
+            |v| {
+                last.set(Some(v));
+                output.push(v);
+            },
+        ))?;
+        assert_eq!(output, vec![6, 10, 18, 34]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel() -> Result<()> {
+        let (in_tx, in_rx) = channel();
+        let (out_tx, out_rx) = channel();
+
+        let handle = thread::spawn(move || -> Result<Status> {
+            let log = Log::new(false);
+            let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+            comp.run_device(&mut ChannelDevice::new(in_rx, out_tx))?;
+            Ok(comp.get_status())
+        });
+
+        in_tx.send(21)?;
+        assert_eq!(out_rx.recv()?, 42);
+        in_tx.send(0)?;
+
+        assert_eq!(handle.join().unwrap()?, Status::Halted);
+        assert!(out_rx.recv().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_text() -> Result<()> {
+        let log = Log::new(false);
+        // Echo input, then output a large value
+        let prog = vec![
+            3, 100, 4, 100, 1008, 100, 10, 101, 1006, 101, 0, 104, 1000, 99,
+        ];
+        let mut comp = IntcodeComp::new(prog, &log);
+        let mut device = TextDevice::new("Hi\nrest".as_bytes(), Vec::new());
+
+        comp.run_device(&mut device)?;
+        assert_eq!(String::from_utf8(device.into_writer())?, "Hi\n1000\n");
+        Ok(())
+    }
+}
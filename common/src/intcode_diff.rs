@@ -0,0 +1,352 @@
+use crate::intcode_comp::*;
+use crate::intcode_memory::*;
+use crate::intcode_snapshot::*;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Memory cell with different values in two states
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Change {
+    pub addr: usize,
+    pub old: DataType,
+    pub new: DataType,
+}
+
+/// Contiguous changed cells starting at `start`
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChangedRange {
+    pub start: usize,
+    pub old: Vec<DataType>,
+    pub new: Vec<DataType>,
+}
+
+impl ChangedRange {
+    /// Address after the last changed cell
+    pub fn end(&self) -> usize {
+        self.start + self.old.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.old.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.old.is_empty()
+    }
+}
+
+impl fmt::Display for ChangedRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |values: &[DataType]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        if self.len() == 1 {
+            write!(f, "[{:4}]", self.start)?;
+        } else {
+            write!(f, "[{:4}..{:4}]", self.start, self.end() - 1)?;
+        }
+        write!(f, " {} -> {}", join(&self.old), join(&self.new))
+    }
+}
+
+/// Changed memory cells between two machine states in address order.
+/// Cells beyond the end of the shorter memory are zeros.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MemoryDiff {
+    changes: Vec<Change>,
+}
+
+impl MemoryDiff {
+    pub fn new(old: &[DataType], new: &[DataType]) -> Self {
+        Self::images(
+            &MemoryImage::from_vec(old.to_vec()),
+            &MemoryImage::from_vec(new.to_vec()),
+        )
+    }
+
+    /// Only cells stored in the images are compared, so far writes to paged memory
+    /// don't make the diff walk the gaps between them
+    pub fn images(old: &MemoryImage, new: &MemoryImage) -> Self {
+        let mut old_cells = old.cells().peekable();
+        let mut new_cells = new.cells().peekable();
+        let mut changes = Vec::new();
+
+        loop {
+            let order = match (old_cells.peek(), new_cells.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((old_addr, _)), Some((new_addr, _))) => old_addr.cmp(new_addr),
+            };
+
+            let change = match order {
+                Ordering::Less => old_cells
+                    .next()
+                    .map(|(addr, old)| Change { addr, old, new: 0 }),
+                Ordering::Greater => {
+                    new_cells
+                        .next()
+                        .map(|(addr, new)| Change { addr, old: 0, new })
+                }
+                Ordering::Equal => old_cells
+                    .next()
+                    .zip(new_cells.next())
+                    .map(|((addr, old), (_, new))| Change { addr, old, new }),
+            };
+
+            changes.extend(change.filter(|change| change.old != change.new));
+        }
+
+        Self { changes }
+    }
+
+    pub fn between(old: &Snapshot, new: &Snapshot) -> Self {
+        Self::images(&old.memory, &new.memory)
+    }
+
+    /// Changes from the memory copy (see `IntcodeComp::memory`) to the current state
+    pub fn since(old: &MemoryImage, comp: &IntcodeComp) -> Self {
+        Self::images(old, &comp.memory())
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn addrs(&self) -> BTreeSet<usize> {
+        self.changes.iter().map(|change| change.addr).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Changes at addresses that are also changed in `other`. Diffs taken around
+    /// the same action repeated several times narrow down the cells of a variable.
+    pub fn intersect(&self, other: &MemoryDiff) -> MemoryDiff {
+        let addrs = other.addrs();
+        let changes = self
+            .changes
+            .iter()
+            .filter(|change| addrs.contains(&change.addr))
+            .copied()
+            .collect();
+
+        Self { changes }
+    }
+
+    pub fn ranges(&self) -> Vec<ChangedRange> {
+        let mut ranges: Vec<ChangedRange> = Vec::new();
+
+        for change in &self.changes {
+            match ranges.last_mut() {
+                Some(range) if range.end() == change.addr => {
+                    range.old.push(change.old);
+                    range.new.push(change.new);
+                }
+                _ => ranges.push(ChangedRange {
+                    start: change.addr,
+                    old: vec![change.old],
+                    new: vec![change.new],
+                }),
+            }
+        }
+
+        ranges
+    }
+}
+
+impl fmt::Display for MemoryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for range in self.ranges() {
+            writeln!(f, "{}", range)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Watch {
+    pub addr: usize,
+    pub name: String,
+    /// Value at the last update
+    pub value: DataType,
+}
+
+/// Named memory cells whose values are tracked while the program runs
+#[derive(Debug, Clone, Default)]
+pub struct WatchList {
+    watches: Vec<Watch>,
+}
+
+impl WatchList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start watching the address with its current value. Watching the same address
+    /// again renames it.
+    pub fn add(&mut self, comp: &IntcodeComp, addr: usize, name: &str) {
+        match self.watches.iter_mut().find(|watch| watch.addr == addr) {
+            Some(watch) => watch.name = name.to_string(),
+            None => self.watches.push(Watch {
+                addr,
+                name: name.to_string(),
+                value: comp.get_mem(addr),
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, addr: usize) -> bool {
+        let len = self.watches.len();
+        self.watches.retain(|watch| watch.addr != addr);
+        self.watches.len() != len
+    }
+
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Read current values and return watches changed since the previous update
+    pub fn update(&mut self, comp: &IntcodeComp) -> Vec<(Watch, DataType)> {
+        let mut changed = Vec::new();
+
+        for watch in &mut self.watches {
+            let value = comp.get_mem(watch.addr);
+            if value != watch.value {
+                let old = watch.value;
+                watch.value = value;
+                changed.push((watch.clone(), old));
+            }
+        }
+
+        changed
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}] = {}", self.name, self.addr, self.value)
+    }
+}
+
+impl fmt::Display for WatchList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for watch in &self.watches {
+            writeln!(f, "{}", watch)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+    use crate::log::*;
+    use anyhow::Result;
+
+    // Moves along x for every input, picks an item at x = 2
+    const PROG: &str = "
+        loop: GET  [cmd]
+              ADD  [x], [cmd], [x]
+              EQ   [x], #2, [t]
+              JIF  [t], #loop
+              ADD  #1, #0, [items]
+              JIT  #1, #loop
+        x:     db 0
+        cmd:   db 0
+        t:     db 0
+        items: db 0
+    ";
+
+    #[test]
+    fn test_diff() {
+        let diff = MemoryDiff::new(&[1, 2, 3, 4, 5], &[1, 0, 0, 4, 6, 0, 7]);
+        assert_eq!(diff.len(), 4);
+        assert_eq!(
+            diff.changes()[0],
+            Change {
+                addr: 1,
+                old: 2,
+                new: 0
+            }
+        );
+
+        let ranges = diff.ranges();
+        assert_eq!(ranges.len(), 3);
+        assert_eq!((ranges[0].start, ranges[0].end()), (1, 3));
+        assert_eq!(ranges[2].new, vec![7]);
+        assert_eq!(
+            diff.to_string(),
+            "[   1..   2] 2, 3 -> 0, 0\n[   4] 5 -> 6\n[   6] 0 -> 7\n"
+        );
+
+        let other = MemoryDiff::new(&[0, 0, 3, 0, 5, 0, 0], &[0; 7]);
+        assert_eq!(
+            diff.intersect(&other).addrs(),
+            [2, 4].iter().copied().collect()
+        );
+        assert!(MemoryDiff::new(&[1, 0, 0], &[1]).is_empty());
+    }
+
+    #[test]
+    fn test_locate_variables() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(assemble(PROG)?, &log);
+        comp.run()?;
+
+        let before = comp.snapshot();
+        comp.add_input(1);
+        comp.run()?;
+        let first = MemoryDiff::between(&before, &comp.snapshot());
+
+        let before = comp.memory();
+        comp.add_input(1);
+        comp.run()?;
+        let second = MemoryDiff::since(&before, &comp);
+
+        // x and the item counter, cmd stays 1 after the first move
+        // Code takes 20 cells
+        let x = 20;
+        assert_eq!(
+            first.intersect(&second).addrs(),
+            [x].iter().copied().collect()
+        );
+        assert_eq!(second.ranges().len(), 2);
+
+        let mut watch = WatchList::new();
+        watch.add(&comp, x, "x");
+        watch.add(&comp, x + 3, "items");
+        assert_eq!(
+            watch.to_string(),
+            format!("x [{}] = 2\nitems [{}] = 1\n", x, x + 3)
+        );
+
+        comp.add_input(-2);
+        comp.run()?;
+        let changed = watch.update(&comp);
+        assert_eq!(changed.len(), 1);
+        assert_eq!((changed[0].0.value, changed[0].1), (0, 2));
+        assert!(watch.update(&comp).is_empty());
+
+        assert!(watch.remove(x));
+        assert!(!watch.remove(x));
+        assert_eq!(watch.watches().len(), 1);
+        Ok(())
+    }
+}
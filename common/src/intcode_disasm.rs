@@ -0,0 +1,412 @@
+use crate::intcode_comp::*;
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Operand {
+    Position(DataType),
+    Immediate(DataType),
+    Relative(DataType),
+    /// Parameter in the mode registered in the `InstructionSet`
+    Custom(u8, DataType),
+}
+
+impl Operand {
+    pub fn new(mode: ParamMode, value: DataType) -> Self {
+        match mode {
+            ParamMode::Position => Operand::Position(value),
+            ParamMode::Immediate => Operand::Immediate(value),
+            ParamMode::Relative => Operand::Relative(value),
+            ParamMode::Custom(mode) => Operand::Custom(mode, value),
+        }
+    }
+
+    pub fn value(&self) -> DataType {
+        match *self {
+            Operand::Position(v)
+            | Operand::Immediate(v)
+            | Operand::Relative(v)
+            | Operand::Custom(_, v) => v,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Position(v) => write!(f, "p[{}]", v),
+            Operand::Immediate(v) => write!(f, "i[{}]", v),
+            Operand::Relative(v) => write!(f, "r[{}]", v),
+            Operand::Custom(mode, v) => write!(f, "m{}[{}]", mode, v),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Entry {
+    Code {
+        addr: usize,
+        cmd: Command,
+        operands: Vec<Operand>,
+        jump_target: bool,
+    },
+    Data {
+        addr: usize,
+        value: DataType,
+    },
+}
+
+impl Entry {
+    pub fn addr(&self) -> usize {
+        match *self {
+            Entry::Code { addr, .. } | Entry::Data { addr, .. } => addr,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Entry::Code { operands, .. } => operands.len() + 1,
+            Entry::Data { .. } => 1,
+        }
+    }
+
+    pub fn is_code(&self) -> bool {
+        matches!(self, Entry::Code { .. })
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, marker: char) -> fmt::Result {
+        match self {
+            Entry::Code {
+                addr,
+                cmd,
+                operands,
+                ..
+            } => {
+                write!(f, "[{:4}]{}{:<4}", addr, marker, cmd.mnemonic())?;
+                let operands: Vec<String> = operands.iter().map(|o| o.to_string()).collect();
+                if !operands.is_empty() {
+                    write!(f, " {}", operands.join(", "))?;
+                }
+                Ok(())
+            }
+            Entry::Data { addr, value } => write!(f, "[{:4}]{}DATA {}", addr, marker, value),
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Code {
+                jump_target: true, ..
+            } => self.write(f, '>'),
+            _ => self.write(f, ' '),
+        }
+    }
+}
+
+/// Entry point inside another instruction, or code from it running into another
+/// instruction. Shown in the listing with `*` after the entry that covers its address.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Overlap(pub Entry);
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(f, '*')
+    }
+}
+
+/// Disassembled program. Every memory cell belongs to exactly one entry,
+/// overlapping entry points are decoded separately.
+#[derive(Debug, Default)]
+pub struct Listing {
+    entries: Vec<Entry>,
+    overlaps: Vec<Overlap>,
+}
+
+impl Listing {
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Entry that covers given address
+    pub fn get(&self, addr: usize) -> Option<&Entry> {
+        let idx = match self.entries.binary_search_by_key(&addr, |e| e.addr()) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+        let entry = &self.entries[idx];
+
+        if addr < entry.addr() + entry.size() {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Overlapping entry points in address order
+    pub fn overlaps(&self) -> &[Overlap] {
+        &self.overlaps
+    }
+
+    /// Overlapping entry points at addresses covered by the entry
+    pub fn overlaps_in<'a>(&'a self, entry: &Entry) -> impl Iterator<Item = &'a Overlap> {
+        let range = entry.addr()..entry.addr() + entry.size();
+        self.overlaps
+            .iter()
+            .filter(move |overlap| range.contains(&overlap.0.addr()))
+    }
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+            for overlap in self.overlaps_in(entry) {
+                writeln!(f, "{}", overlap)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Recursive descent disassembler. Starts from address 0 (and any extra entry points)
+/// and follows fall through paths and jumps with immediate targets.
+/// Cells that were not reached are reported as data. Paths that enter or run into
+/// already decoded instructions are recorded as overlaps.
+pub struct Disassembler<'p> {
+    prog: &'p [DataType],
+    entry_points: BTreeSet<usize>,
+}
+
+impl<'p> Disassembler<'p> {
+    pub fn new(prog: &'p [DataType]) -> Self {
+        let mut entry_points = BTreeSet::new();
+        entry_points.insert(0);
+
+        Self { prog, entry_points }
+    }
+
+    /// Add address of the code that can't be found by following jumps (e.g. return addresses)
+    pub fn add_entry_point(&mut self, addr: usize) {
+        self.entry_points.insert(addr);
+    }
+
+    pub fn disassemble(&self) -> Listing {
+        let mut owner: Vec<Option<usize>> = vec![None; self.prog.len()];
+        let mut code: Vec<(usize, Command, Vec<Operand>)> = Vec::new();
+        let mut targets = BTreeSet::new();
+        let mut overlaps = BTreeSet::new();
+        let mut queue: Vec<usize> = self.entry_points.iter().rev().cloned().collect();
+
+        while let Some(mut addr) = queue.pop() {
+            while addr < self.prog.len() {
+                if let Some(idx) = owner[addr] {
+                    if code[idx].0 != addr {
+                        overlaps.insert(addr);
+                    }
+                    break;
+                }
+
+                let (cmd, operands) = match self.decode(addr) {
+                    Some(res) => res,
+                    None => break,
+                };
+
+                let end = addr + operands.len() + 1;
+                if owner[addr..end].iter().any(|o| o.is_some()) {
+                    overlaps.insert(addr);
+                    break;
+                }
+
+                owner[addr..end]
+                    .iter_mut()
+                    .for_each(|o| *o = Some(code.len()));
+
+                let mut fall_through = true;
+                match (cmd, operands.as_slice()) {
+                    (Command::Exit, _) => fall_through = false,
+                    (Command::JumpIfTrue(..), [cond, target])
+                    | (Command::JumpIfFalse(..), [cond, target]) => {
+                        // Immediate condition: jump is either always or never taken
+                        let jumps = match cond {
+                            Operand::Immediate(cond) => Some(match cmd {
+                                Command::JumpIfTrue(..) => *cond != 0,
+                                _ => *cond == 0,
+                            }),
+                            _ => None,
+                        };
+                        if let (Operand::Immediate(target), Some(true) | None) = (target, jumps) {
+                            if *target >= 0 {
+                                targets.insert(*target as usize);
+                                queue.push(*target as usize);
+                            }
+                        }
+                        fall_through = jumps != Some(true);
+                    }
+                    _ => {}
+                }
+
+                code.push((addr, cmd, operands));
+
+                if !fall_through {
+                    break;
+                }
+                addr = end;
+            }
+        }
+
+        let mut entries = Vec::new();
+        let mut addr = 0;
+
+        while addr < self.prog.len() {
+            match owner[addr] {
+                Some(idx) => {
+                    let (code_addr, cmd, operands) = &code[idx];
+                    entries.push(Entry::Code {
+                        addr: *code_addr,
+                        cmd: *cmd,
+                        operands: operands.clone(),
+                        jump_target: targets.contains(code_addr),
+                    });
+                    addr += operands.len() + 1;
+                }
+                None => {
+                    entries.push(Entry::Data {
+                        addr,
+                        value: self.prog[addr],
+                    });
+                    addr += 1;
+                }
+            }
+        }
+
+        let overlaps = overlaps
+            .into_iter()
+            .map(|addr| {
+                Overlap(match self.decode(addr) {
+                    Some((cmd, operands)) => Entry::Code {
+                        addr,
+                        cmd,
+                        operands,
+                        jump_target: targets.contains(&addr),
+                    },
+                    None => Entry::Data {
+                        addr,
+                        value: self.prog[addr],
+                    },
+                })
+            })
+            .collect();
+
+        Listing { entries, overlaps }
+    }
+
+    /// Instruction at the address if it can be decoded and fits the program
+    fn decode(&self, addr: usize) -> Option<(Command, Vec<Operand>)> {
+        let (cmd, params_count) = Command::parse(addr, self.prog[addr]).ok()?;
+        let end = addr + params_count + 1;
+        if end > self.prog.len() {
+            return None;
+        }
+
+        let operands = cmd
+            .param_modes()
+            .into_iter()
+            .zip(&self.prog[addr + 1..end])
+            .map(|(mode, value)| Operand::new(mode, *value))
+            .collect();
+
+        Some((cmd, operands))
+    }
+}
+
+pub fn disassemble(prog: &[DataType]) -> Listing {
+    Disassembler::new(prog).disassemble()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linear() {
+        let listing = disassemble(&[1002, 4, 3, 4, 33]);
+
+        assert_eq!(
+            listing.to_string(),
+            "[   0] MUL  p[4], i[3], p[4]\n[   4] DATA 33\n"
+        );
+    }
+
+    #[test]
+    fn test_follow_jumps() {
+        // Jump over the data block, then read with relative mode and halt
+        let prog = vec![1105, 1, 5, 7, 7, 203, -1, 99];
+        let listing = disassemble(&prog);
+
+        assert_eq!(
+            listing.to_string(),
+            "[   0] JIT  i[1], i[5]\n[   3] DATA 7\n[   4] DATA 7\n[   5]>GET  r[-1]\n[   7] EXIT\n"
+        );
+        assert!(listing.get(6).unwrap().is_code());
+        assert_eq!(listing.get(6).unwrap().addr(), 5);
+        assert!(!listing.get(3).unwrap().is_code());
+        assert_eq!(listing.get(8), None);
+    }
+
+    #[test]
+    fn test_undecodable() {
+        let listing = disassemble(&[3, 0, 4, 0, 55, 99]);
+
+        assert_eq!(
+            listing.to_string(),
+            "[   0] GET  p[0]\n[   2] SET  p[0]\n[   4] DATA 55\n[   5] DATA 99\n"
+        );
+    }
+
+    #[test]
+    fn test_entry_point() {
+        let prog = vec![99, 104, 42, 99];
+        let mut disasm = Disassembler::new(&prog);
+        disasm.add_entry_point(1);
+
+        assert_eq!(
+            disasm.disassemble().to_string(),
+            "[   0] EXIT\n[   1] SET  i[42]\n[   3] EXIT\n"
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        // Jump that is never taken doesn't make its target code
+        let listing = disassemble(&[1105, 0, 4, 1101, 99, 0, 0, 99]);
+        assert_eq!(
+            listing.to_string(),
+            "[   0] JIT  i[0], i[4]\n[   3] ADD  i[99], i[0], p[0]\n[   7] EXIT\n"
+        );
+        assert!(listing.overlaps().is_empty());
+
+        // Jump into the middle of ADD
+        let listing = disassemble(&[1005, 8, 4, 1101, 99, 0, 0, 99, 0]);
+        assert_eq!(
+            listing.to_string(),
+            "[   0] JIT  p[8], i[4]\n[   3] ADD  i[99], i[0], p[0]\n[   4]*EXIT\n[   7] EXIT\n\
+             [   8] DATA 0\n"
+        );
+        assert_eq!(listing.overlaps().len(), 1);
+        assert_eq!(listing.get(4).unwrap().addr(), 3);
+
+        // Code from the entry point runs into EXIT at the jump target
+        let prog = vec![1105, 1, 7, 0, 0, 1, 0, 99, 0];
+        let mut disasm = Disassembler::new(&prog);
+        disasm.add_entry_point(5);
+        let listing = disasm.disassemble();
+        assert_eq!(
+            listing.to_string(),
+            "[   0] JIT  i[1], i[7]\n[   3] DATA 0\n[   4] DATA 0\n[   5] DATA 1\n\
+             [   5]*ADD  p[0], p[99], p[0]\n[   6] DATA 0\n[   7]>EXIT\n[   8] DATA 0\n"
+        );
+    }
+}
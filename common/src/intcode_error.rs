@@ -0,0 +1,220 @@
+use crate::intcode_comp::DataType;
+use std::fmt;
+use std::time::Duration;
+
+/// Runtime faults of the `IntcodeComp`. Every variant carries instruction pointer
+/// and raw opcode of the failed instruction.
+#[derive(Debug, PartialEq, Clone)]
+pub enum IntcodeError {
+    InvalidOpcode {
+        ip: usize,
+        opcode: DataType,
+    },
+    InvalidParamMode {
+        ip: usize,
+        opcode: DataType,
+        mode: DataType,
+    },
+    WriteToImmediate {
+        ip: usize,
+        opcode: DataType,
+    },
+    NegativeAddress {
+        ip: usize,
+        opcode: DataType,
+        addr: DataType,
+    },
+    Halted {
+        ip: usize,
+        opcode: DataType,
+    },
+    MemoryLimit {
+        ip: usize,
+        opcode: DataType,
+        addr: DataType,
+        max_addr: usize,
+    },
+    InstructionLimit {
+        ip: usize,
+        opcode: DataType,
+        limit: u64,
+    },
+    Timeout {
+        ip: usize,
+        opcode: DataType,
+        timeout: Duration,
+    },
+    Cancelled {
+        ip: usize,
+        opcode: DataType,
+    },
+    /// Result of ADD or MUL doesn't fit `DataType` in the checked arithmetic mode
+    Overflow {
+        ip: usize,
+        opcode: DataType,
+    },
+}
+
+impl IntcodeError {
+    pub fn ip(&self) -> usize {
+        match *self {
+            IntcodeError::InvalidOpcode { ip, .. }
+            | IntcodeError::InvalidParamMode { ip, .. }
+            | IntcodeError::WriteToImmediate { ip, .. }
+            | IntcodeError::NegativeAddress { ip, .. }
+            | IntcodeError::Halted { ip, .. }
+            | IntcodeError::MemoryLimit { ip, .. }
+            | IntcodeError::InstructionLimit { ip, .. }
+            | IntcodeError::Timeout { ip, .. }
+            | IntcodeError::Cancelled { ip, .. }
+            | IntcodeError::Overflow { ip, .. } => ip,
+        }
+    }
+
+    pub fn opcode(&self) -> DataType {
+        match *self {
+            IntcodeError::InvalidOpcode { opcode, .. }
+            | IntcodeError::InvalidParamMode { opcode, .. }
+            | IntcodeError::WriteToImmediate { opcode, .. }
+            | IntcodeError::NegativeAddress { opcode, .. }
+            | IntcodeError::Halted { opcode, .. }
+            | IntcodeError::MemoryLimit { opcode, .. }
+            | IntcodeError::InstructionLimit { opcode, .. }
+            | IntcodeError::Timeout { opcode, .. }
+            | IntcodeError::Cancelled { opcode, .. }
+            | IntcodeError::Overflow { opcode, .. } => opcode,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntcodeError::InvalidOpcode { opcode, .. } => {
+                write!(f, "Unknown command id {}.", opcode % 100)?
+            }
+            IntcodeError::InvalidParamMode { mode, .. } => {
+                write!(f, "Unknown parameter mode {}.", mode)?
+            }
+            IntcodeError::WriteToImmediate { .. } => write!(
+                f,
+                "Wrong destination parameter. Expected Position or Relative but was Immediate."
+            )?,
+            IntcodeError::NegativeAddress { addr, .. } => write!(f, "Negative address {}.", addr)?,
+            IntcodeError::Halted { .. } => write!(f, "Program was halted.")?,
+            IntcodeError::MemoryLimit { addr, max_addr, .. } => {
+                write!(f, "Address {} exceeds memory limit {}.", addr, max_addr)?
+            }
+            IntcodeError::InstructionLimit { limit, .. } => {
+                write!(f, "Instruction limit {} is reached.", limit)?
+            }
+            IntcodeError::Timeout { timeout, .. } => {
+                write!(f, "Timeout {:?} is reached.", timeout)?
+            }
+            IntcodeError::Cancelled { .. } => write!(f, "Execution was cancelled.")?,
+            IntcodeError::Overflow { .. } => write!(f, "Arithmetic overflow.")?,
+        }
+
+        write!(f, " ip={} opcode={}", self.ip(), self.opcode())
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_comp::*;
+    use crate::log::*;
+
+    fn run_err(prog: Vec<DataType>) -> IntcodeError {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(prog, &log);
+        comp.set_arithmetic(Arithmetic::Checked);
+
+        comp.exec().unwrap_err().downcast::<IntcodeError>().unwrap()
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run_err(vec![1, 0, 0, 0, 42]),
+            IntcodeError::InvalidOpcode { ip: 4, opcode: 42 }
+        );
+        assert_eq!(
+            run_err(vec![301, 0, 0, 0]),
+            IntcodeError::InvalidParamMode {
+                ip: 0,
+                opcode: 301,
+                mode: 3
+            }
+        );
+        assert_eq!(
+            run_err(vec![11101, 1, 1, 0]),
+            IntcodeError::WriteToImmediate {
+                ip: 0,
+                opcode: 11101
+            }
+        );
+        assert_eq!(
+            run_err(vec![109, 5, 204, -6]),
+            IntcodeError::NegativeAddress {
+                ip: 2,
+                opcode: 204,
+                addr: -1
+            }
+        );
+        assert_eq!(
+            run_err(vec![1102, DataType::MAX, 2, 0]),
+            IntcodeError::Overflow {
+                ip: 0,
+                opcode: 1102
+            }
+        );
+
+        let err = run_err(vec![4, 1_000_000_000]);
+        assert_eq!(
+            err,
+            IntcodeError::MemoryLimit {
+                ip: 0,
+                opcode: 4,
+                addr: 1_000_000_000,
+                max_addr: crate::intcode_memory::DEFAULT_MAX_ADDR,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Address 1000000000 exceeds memory limit 16777215. ip=0 opcode=4"
+        );
+    }
+
+    #[test]
+    fn test_halted() {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(vec![99], &log);
+
+        comp.exec().unwrap();
+
+        let err = comp.run().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<IntcodeError>(),
+            Some(&IntcodeError::Halted { ip: 0, opcode: 99 })
+        );
+
+        // Resumed after the first EXIT, halted by the second one
+        let mut comp = IntcodeComp::new(vec![104, 1, 99, 104, 2, 99], &log);
+        comp.exec().unwrap();
+        comp.start();
+        comp.exec().unwrap();
+        assert_eq!(comp.get_output(), vec![1, 2]);
+
+        let restored = IntcodeComp::from_snapshot(comp.snapshot(), &log).unwrap();
+        for comp in &mut [comp, restored] {
+            let err = comp.step().unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<IntcodeError>(),
+                Some(&IntcodeError::Halted { ip: 5, opcode: 99 })
+            );
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use crate::intcode_comp::*;
+use crate::intcode_device::*;
+use anyhow::{anyhow, ensure, Result};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ExecStatus {
+    /// All machines are halted
+    Halted,
+    /// Every running machine waits for input that nobody can send
+    Deadlock,
+    /// Some machine was stopped by its execution limits
+    Interrupted,
+}
+
+pub struct ExecReport<'l> {
+    pub status: ExecStatus,
+    /// Machines in the state they were stopped in
+    pub machines: Vec<IntcodeComp<'l>>,
+    /// All values produced by every machine
+    pub outputs: Vec<Vec<DataType>>,
+}
+
+/// Runs every machine on its own thread. Output of a machine is sent to the input
+/// channels of all machines connected to it. Reading from the empty input blocks.
+pub struct Executor<'l> {
+    machines: Vec<IntcodeComp<'l>>,
+    links: Vec<Vec<usize>>,
+}
+
+impl<'l> Executor<'l> {
+    pub fn new() -> Self {
+        Self {
+            machines: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /// Returns machine id. Values already in the machine input queue are consumed first.
+    pub fn add(&mut self, comp: IntcodeComp<'l>) -> usize {
+        self.machines.push(comp);
+        self.links.push(Vec::new());
+        self.machines.len() - 1
+    }
+
+    /// Send output of the `from` machine to the input of the `to` machine
+    pub fn connect(&mut self, from: usize, to: usize) -> Result<()> {
+        ensure!(
+            from < self.machines.len() && to < self.machines.len(),
+            "Wrong link {} -> {}. Machines count: {}",
+            from,
+            to,
+            self.machines.len()
+        );
+
+        self.links[from].push(to);
+
+        Ok(())
+    }
+
+    /// Run until all machines halt or deadlock is detected
+    pub fn run(self) -> Result<ExecReport<'l>> {
+        let Self { machines, links } = self;
+        let count = machines.len();
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..count).map(|_| channel()).unzip();
+        let monitor = Monitor::new(senders);
+        let links = &links;
+
+        let results: Vec<Result<(IntcodeComp<'l>, Vec<DataType>)>> = thread::scope(|s| {
+            let handles: Vec<_> = machines
+                .into_iter()
+                .zip(receivers)
+                .enumerate()
+                .map(|(id, (mut comp, input))| {
+                    let monitor = &monitor;
+                    s.spawn(move || {
+                        let mut port = Port {
+                            input,
+                            links: &links[id],
+                            monitor,
+                            outputs: Vec::new(),
+                        };
+                        let result = comp.run_device(&mut port);
+
+                        monitor.finish(id, &port.input, result.is_err());
+
+                        result.map(|_| (comp, port.outputs))
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| {
+                    h.join()
+                        .map_err(|_| anyhow!("Intcode machine thread panicked"))?
+                })
+                .collect()
+        });
+
+        let mut machines = Vec::with_capacity(count);
+        let mut outputs = Vec::with_capacity(count);
+        for result in results {
+            let (comp, output) = result?;
+            machines.push(comp);
+            outputs.push(output);
+        }
+
+        let status = if machines.iter().any(|m| m.is_interrupted()) {
+            ExecStatus::Interrupted
+        } else if monitor.lock().deadlock {
+            ExecStatus::Deadlock
+        } else {
+            ExecStatus::Halted
+        };
+
+        Ok(ExecReport {
+            status,
+            machines,
+            outputs,
+        })
+    }
+}
+
+impl<'l> Default for Executor<'l> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Message {
+    Value(DataType),
+    Stop,
+}
+
+struct MonitorState {
+    alive: Vec<bool>,
+    waiting: usize,
+    /// Values that were sent but not received yet
+    in_flight: usize,
+    deadlock: bool,
+    stopped: bool,
+}
+
+/// Tracks machines state to detect deadlock
+struct Monitor {
+    state: Mutex<MonitorState>,
+    senders: Vec<Sender<Message>>,
+}
+
+impl Monitor {
+    fn new(senders: Vec<Sender<Message>>) -> Self {
+        Self {
+            state: Mutex::new(MonitorState {
+                alive: vec![true; senders.len()],
+                waiting: 0,
+                in_flight: 0,
+                deadlock: false,
+                stopped: false,
+            }),
+            senders,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MonitorState> {
+        // State stays consistent even if some thread panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn send(&self, to: usize, value: DataType) {
+        let mut state = self.lock();
+
+        if state.alive[to] && self.senders[to].send(Message::Value(value)).is_ok() {
+            state.in_flight += 1;
+        }
+    }
+
+    /// Returns false if machine should not wait for input
+    fn begin_wait(&self) -> bool {
+        let mut state = self.lock();
+
+        if state.stopped {
+            return false;
+        }
+
+        state.waiting += 1;
+        self.check_deadlock(&mut state);
+
+        !state.stopped
+    }
+
+    fn end_wait(&self, received: bool) {
+        let mut state = self.lock();
+
+        state.waiting -= 1;
+        if received {
+            state.in_flight -= 1;
+        }
+    }
+
+    fn finish(&self, id: usize, input: &Receiver<Message>, failed: bool) {
+        let mut state = self.lock();
+
+        state.alive[id] = false;
+        while let Ok(message) = input.try_recv() {
+            if let Message::Value(_) = message {
+                state.in_flight -= 1;
+            }
+        }
+
+        if failed {
+            self.stop(&mut state);
+        } else {
+            self.check_deadlock(&mut state);
+        }
+    }
+
+    fn check_deadlock(&self, state: &mut MonitorState) {
+        let alive = state.alive.iter().filter(|a| **a).count();
+
+        if !state.stopped && alive > 0 && state.waiting == alive && state.in_flight == 0 {
+            state.deadlock = true;
+            self.stop(state);
+        }
+    }
+
+    fn stop(&self, state: &mut MonitorState) {
+        state.stopped = true;
+
+        for sender in &self.senders {
+            let _ = sender.send(Message::Stop);
+        }
+    }
+}
+
+struct Port<'a> {
+    input: Receiver<Message>,
+    links: &'a [usize],
+    monitor: &'a Monitor,
+    outputs: Vec<DataType>,
+}
+
+impl<'a> Device for Port<'a> {
+    fn input(&mut self) -> Result<Option<DataType>> {
+        if !self.monitor.begin_wait() {
+            return Ok(None);
+        }
+
+        let value = match self.input.recv() {
+            Ok(Message::Value(value)) => Some(value),
+            Ok(Message::Stop) | Err(_) => None,
+        };
+
+        self.monitor.end_wait(value.is_some());
+
+        Ok(value)
+    }
+
+    fn output(&mut self, value: DataType) -> Result<()> {
+        self.outputs.push(value);
+
+        for &to in self.links {
+            self.monitor.send(to, value);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+
+    // Day 7 feedback loop example. Max thruster signal 139629729 for phases 9,8,7,6,5.
+    const AMP: &str =
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+
+    #[test]
+    fn test_feedback_loop() -> Result<()> {
+        let log = Log::new(false);
+        let mut executor = Executor::new();
+
+        for phase in &[9, 8, 7, 6, 5] {
+            let mut comp = IntcodeComp::new(Vec::new(), &log);
+            comp.load_prog(AMP)?;
+            comp.add_input(*phase);
+            executor.add(comp);
+        }
+        for i in 0..5 {
+            executor.connect(i, (i + 1) % 5)?;
+        }
+        executor.machines[0].add_input(0);
+
+        let report = executor.run()?;
+        assert_eq!(report.status, ExecStatus::Halted);
+        assert_eq!(report.outputs[4].last(), Some(&139_629_729));
+        assert!(report.machines.iter().all(|m| m.is_halted()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deadlock() -> Result<()> {
+        let log = Log::new(false);
+        let mut executor = Executor::new();
+
+        // Both machines read before writing
+        let echo = vec![3, 9, 4, 9, 1105, 1, 0, 99, 0, 0];
+        let a = executor.add(IntcodeComp::new(echo.clone(), &log));
+        let b = executor.add(IntcodeComp::new(echo, &log));
+        executor.connect(a, b)?;
+        executor.connect(b, a)?;
+
+        let report = executor.run()?;
+        assert_eq!(report.status, ExecStatus::Deadlock);
+        assert!(report
+            .machines
+            .iter()
+            .all(|m| m.get_status() == Status::WaitForInput));
+
+        // Values are passed around until machine that halts breaks the loop
+        let mut executor = Executor::new();
+        let mut first = IntcodeComp::new(vec![3, 7, 4, 7, 104, 5, 99, 0], &log);
+        first.add_input(1);
+        let a = executor.add(first);
+        let b = executor.add(IntcodeComp::new(
+            vec![3, 9, 4, 9, 1105, 1, 0, 99, 0, 0],
+            &log,
+        ));
+        executor.connect(a, b)?;
+        executor.connect(b, a)?;
+
+        let report = executor.run()?;
+        assert_eq!(report.status, ExecStatus::Deadlock);
+        assert_eq!(report.outputs, vec![vec![1, 5], vec![1, 5]]);
+        assert!(report.machines[a].is_halted());
+        Ok(())
+    }
+
+    #[test]
+    fn test_error() {
+        let log = Log::new(false);
+        let mut executor = Executor::new();
+
+        let a = executor.add(IntcodeComp::new(vec![3, 0, 99], &log));
+        executor.add(IntcodeComp::new(vec![42], &log));
+        executor.connect(a, a).unwrap();
+
+        assert!(executor.run().is_err());
+    }
+}
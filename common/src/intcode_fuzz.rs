@@ -0,0 +1,631 @@
+use crate::intcode_comp::*;
+use crate::intcode_error::*;
+use crate::intcode_limits::*;
+use crate::log::*;
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    pub seed: u64,
+    pub iterations: usize,
+    /// Programs are stopped after this number of instructions
+    pub max_steps: u64,
+    /// Memory limit, small enough to be hit by generated addresses
+    pub max_addr: usize,
+    pub decode_cache: bool,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            seed: 2019,
+            iterations: 1000,
+            max_steps: 500,
+            max_addr: 1023,
+            decode_cache: true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Stop {
+    Halted,
+    WaitForInput,
+    StepLimit,
+    Fault(IntcodeError),
+    /// Other error reported by the machine
+    Error(String),
+}
+
+/// Machine state after the run. Trailing zeros of the memory are dropped.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Outcome {
+    pub stop: Stop,
+    pub ip: usize,
+    pub rel_base: usize,
+    pub steps: u64,
+    pub output: Vec<DataType>,
+    pub memory: Vec<DataType>,
+}
+
+/// Edge cases hit by the generated programs
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct Coverage {
+    pub runs: usize,
+    /// Programs faulted on arithmetic overflow
+    pub overflow: usize,
+    pub halted: usize,
+    pub waiting: usize,
+    pub step_limit: usize,
+    pub faults: usize,
+    /// Programs accessing memory using negative relative offsets
+    pub negative_relative: usize,
+    /// Programs writing past the end of the program image
+    pub past_end: usize,
+    /// Programs overwriting already executed instructions
+    pub self_modifying: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// Number of the run that diverged, starting from 0
+    pub iteration: usize,
+    /// Minimized program and input
+    pub prog: Vec<DataType>,
+    pub input: Vec<DataType>,
+    pub expected: Outcome,
+    pub actual: Outcome,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prog: Vec<String> = self.prog.iter().map(|v| v.to_string()).collect();
+        writeln!(f, "Run {} diverged", self.iteration)?;
+        writeln!(f, "Program: {}", prog.join(","))?;
+        writeln!(f, "Input: {:?}", self.input)?;
+        writeln!(f, "Reference: {:?}", self.expected)?;
+        write!(f, "Actual: {:?}", self.actual)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzReport {
+    pub coverage: Coverage,
+    pub divergence: Option<Divergence>,
+}
+
+/// Compare `IntcodeComp` with the reference interpreter on random programs
+pub fn fuzz(config: &FuzzConfig) -> FuzzReport {
+    fuzz_with(config, comp_run)
+}
+
+/// Compare `vm` with the reference interpreter on random programs.
+/// Stops at the first divergence and minimizes its program and input.
+pub fn fuzz_with<F>(config: &FuzzConfig, vm: F) -> FuzzReport
+where
+    F: Fn(&[DataType], &[DataType], &FuzzConfig) -> Outcome,
+{
+    let mut rng = Rng::new(config.seed);
+    let mut coverage = Coverage::default();
+
+    for iteration in 0..config.iterations {
+        let (prog, input) = generate(&mut rng, config.max_addr);
+        let mut reference = Reference::new(&prog, &input, config.max_addr);
+        let expected = reference.run(config.max_steps);
+
+        reference.account(&expected, &mut coverage);
+
+        if vm(&prog, &input, config) != expected {
+            let (prog, input) = minimize(&prog, &input, config, &vm);
+            let expected = reference_run(&prog, &input, config);
+            let actual = vm(&prog, &input, config);
+
+            return FuzzReport {
+                coverage,
+                divergence: Some(Divergence {
+                    iteration,
+                    prog,
+                    input,
+                    expected,
+                    actual,
+                }),
+            };
+        }
+    }
+
+    FuzzReport {
+        coverage,
+        divergence: None,
+    }
+}
+
+/// Shrink diverging program and input while they still diverge:
+/// drop cells, move values towards zero and drop input values
+pub fn minimize<F>(
+    prog: &[DataType],
+    input: &[DataType],
+    config: &FuzzConfig,
+    vm: &F,
+) -> (Vec<DataType>, Vec<DataType>)
+where
+    F: Fn(&[DataType], &[DataType], &FuzzConfig) -> Outcome,
+{
+    let diverges = |prog: &[DataType], input: &[DataType]| {
+        vm(prog, input, config) != reference_run(prog, input, config)
+    };
+
+    let mut prog = prog.to_vec();
+    let mut input = input.to_vec();
+
+    loop {
+        let mut progress = false;
+
+        let mut chunk = (prog.len() / 2).max(1);
+        while chunk > 0 {
+            let mut start = 0;
+            while start + chunk <= prog.len() {
+                let mut candidate = prog.clone();
+                candidate.drain(start..start + chunk);
+                if diverges(&candidate, &input) {
+                    prog = candidate;
+                    progress = true;
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+
+        for idx in 0..prog.len() {
+            for value in &[0, prog[idx] / 2] {
+                if *value == prog[idx] {
+                    continue;
+                }
+                let mut candidate = prog.clone();
+                candidate[idx] = *value;
+                if diverges(&candidate, &input) {
+                    prog = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+
+        for idx in (0..input.len()).rev() {
+            let mut candidate = input.clone();
+            candidate.remove(idx);
+            if diverges(&prog, &candidate) {
+                input = candidate;
+                progress = true;
+            }
+        }
+
+        if !progress {
+            return (prog, input);
+        }
+    }
+}
+
+/// Run program on the reference interpreter
+pub fn reference_run(prog: &[DataType], input: &[DataType], config: &FuzzConfig) -> Outcome {
+    Reference::new(prog, input, config.max_addr).run(config.max_steps)
+}
+
+/// Run program on `IntcodeComp`
+pub fn comp_run(prog: &[DataType], input: &[DataType], config: &FuzzConfig) -> Outcome {
+    let log = Log::new(false);
+    let mut comp = IntcodeComp::new(prog.to_vec(), &log);
+    comp.set_max_addr(config.max_addr);
+    comp.set_instruction_limit(Some(config.max_steps));
+    comp.set_decode_cache(config.decode_cache);
+    // Reference interpreter faults on overflow
+    comp.set_arithmetic(Arithmetic::Checked);
+    comp.add_input_vec(&mut input.to_vec());
+
+    let result = comp.run();
+
+    Outcome {
+        stop: stop(result, comp.get_status()),
+        ip: comp.get_ip(),
+        rel_base: comp.get_rel_base(),
+        steps: comp.get_steps(),
+        output: comp.output().to_vec(),
+        memory: trimmed(comp.memory().to_vec()),
+    }
+}
+
+/// Why the machine returned from `run` with `status`
+pub(crate) fn stop(result: anyhow::Result<()>, status: Status) -> Stop {
+    match result {
+        Ok(()) => match status {
+            Status::Halted => Stop::Halted,
+            Status::WaitForInput => Stop::WaitForInput,
+            Status::Interrupted(Interrupt::InstructionLimit) => Stop::StepLimit,
+            status => Stop::Error(format!("Unexpected status {:?}", status)),
+        },
+        Err(err) => match err.downcast::<IntcodeError>() {
+            Ok(fault) => Stop::Fault(fault),
+            Err(err) => Stop::Error(err.to_string()),
+        },
+    }
+}
+
+pub(crate) fn trimmed(mut memory: Vec<DataType>) -> Vec<DataType> {
+    while memory.last() == Some(&0) {
+        memory.pop();
+    }
+    memory
+}
+
+/// Straightforward interpreter following the puzzle descriptions. Jump targets
+/// are read only when the jump is taken, faulted instruction doesn't change the state,
+/// ADD and MUL fault on overflow like `Arithmetic::Checked`.
+struct Reference<'a> {
+    mem: Vec<DataType>,
+    ip: usize,
+    rel_base: usize,
+    input: &'a [DataType],
+    output: Vec<DataType>,
+    steps: u64,
+    max_addr: usize,
+    prog_len: usize,
+    executed: BTreeSet<usize>,
+    negative_relative: bool,
+    past_end: bool,
+    self_modifying: bool,
+}
+
+impl<'a> Reference<'a> {
+    fn new(prog: &[DataType], input: &'a [DataType], max_addr: usize) -> Self {
+        Self {
+            mem: prog.to_vec(),
+            ip: 0,
+            rel_base: 0,
+            input,
+            output: Vec::new(),
+            steps: 0,
+            max_addr,
+            prog_len: prog.len(),
+            executed: BTreeSet::new(),
+            negative_relative: false,
+            past_end: false,
+            self_modifying: false,
+        }
+    }
+
+    fn run(&mut self, max_steps: u64) -> Outcome {
+        let stop = loop {
+            if self.steps >= max_steps {
+                break Stop::StepLimit;
+            }
+            if let Err(stop) = self.step() {
+                break stop;
+            }
+        };
+
+        Outcome {
+            stop,
+            ip: self.ip,
+            rel_base: self.rel_base,
+            steps: self.steps,
+            output: self.output.clone(),
+            memory: trimmed(self.mem.clone()),
+        }
+    }
+
+    fn account(&self, outcome: &Outcome, coverage: &mut Coverage) {
+        coverage.runs += 1;
+        match outcome.stop {
+            Stop::Halted => coverage.halted += 1,
+            Stop::WaitForInput => coverage.waiting += 1,
+            Stop::StepLimit => coverage.step_limit += 1,
+            Stop::Fault(IntcodeError::Overflow { .. }) => {
+                coverage.faults += 1;
+                coverage.overflow += 1;
+            }
+            Stop::Fault(_) | Stop::Error(_) => coverage.faults += 1,
+        }
+        coverage.negative_relative += self.negative_relative as usize;
+        coverage.past_end += self.past_end as usize;
+        coverage.self_modifying += self.self_modifying as usize;
+    }
+
+    fn read(&self, addr: usize) -> DataType {
+        self.mem.get(addr).cloned().unwrap_or(0)
+    }
+
+    fn check(&self, opcode: DataType, addr: DataType) -> Result<usize, Stop> {
+        let ip = self.ip;
+        if addr < 0 {
+            Err(Stop::Fault(IntcodeError::NegativeAddress {
+                ip,
+                opcode,
+                addr,
+            }))
+        } else if addr as u64 > self.max_addr as u64 {
+            Err(Stop::Fault(IntcodeError::MemoryLimit {
+                ip,
+                opcode,
+                addr,
+                max_addr: self.max_addr,
+            }))
+        } else {
+            Ok(addr as usize)
+        }
+    }
+
+    /// Address of the parameter `idx` (starting from 0) in position or relative mode
+    fn addr(&mut self, opcode: DataType, idx: usize, mode: DataType) -> Result<usize, Stop> {
+        let raw = self.read(self.ip + idx + 1);
+        if mode == 2 {
+            self.negative_relative |= raw < 0;
+            self.check(opcode, (self.rel_base as DataType).saturating_add(raw))
+        } else {
+            self.check(opcode, raw)
+        }
+    }
+
+    fn load(&mut self, opcode: DataType, idx: usize, mode: DataType) -> Result<DataType, Stop> {
+        if mode == 1 {
+            return Ok(self.read(self.ip + idx + 1));
+        }
+        let addr = self.addr(opcode, idx, mode)?;
+        Ok(self.read(addr))
+    }
+
+    fn store(
+        &mut self,
+        opcode: DataType,
+        idx: usize,
+        mode: DataType,
+        value: DataType,
+    ) -> Result<(), Stop> {
+        if mode == 1 {
+            let ip = self.ip;
+            return Err(Stop::Fault(IntcodeError::WriteToImmediate { ip, opcode }));
+        }
+        let addr = self.addr(opcode, idx, mode)?;
+
+        self.past_end |= addr >= self.prog_len;
+        self.self_modifying |= self.executed.contains(&addr);
+
+        if addr >= self.mem.len() {
+            self.mem.resize(addr + 1, 0);
+        }
+        self.mem[addr] = value;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<(), Stop> {
+        let ip = self.ip;
+        let opcode = self.read(ip);
+
+        let arity = match opcode % 100 {
+            _ if opcode <= 0 => None,
+            1 | 2 | 7 | 8 => Some(3),
+            5 | 6 => Some(2),
+            3 | 4 | 9 => Some(1),
+            99 => Some(0),
+            _ => None,
+        }
+        .ok_or(Stop::Fault(IntcodeError::InvalidOpcode { ip, opcode }))?;
+
+        let mut modes = Vec::new();
+        for idx in 0..arity {
+            let mode = opcode / [100, 1000, 10000][idx] % 10;
+            if mode > 2 {
+                return Err(Stop::Fault(IntcodeError::InvalidParamMode {
+                    ip,
+                    opcode,
+                    mode,
+                }));
+            }
+            modes.push(mode);
+        }
+
+        self.executed.insert(ip);
+        let mut next_ip = ip + arity + 1;
+
+        match opcode % 100 {
+            1 | 2 | 7 | 8 => {
+                let a = self.load(opcode, 0, modes[0])?;
+                let b = self.load(opcode, 1, modes[1])?;
+                let overflow = Stop::Fault(IntcodeError::Overflow { ip, opcode });
+                let value = match opcode % 100 {
+                    1 => a.checked_add(b).ok_or(overflow)?,
+                    2 => a.checked_mul(b).ok_or(overflow)?,
+                    7 => (a < b) as DataType,
+                    _ => (a == b) as DataType,
+                };
+                self.store(opcode, 2, modes[2], value)?;
+            }
+            3 => {
+                let value = match self.input.split_first() {
+                    Some((value, rest)) => {
+                        self.input = rest;
+                        *value
+                    }
+                    None => return Err(Stop::WaitForInput),
+                };
+                self.store(opcode, 0, modes[0], value)?;
+            }
+            4 => {
+                let value = self.load(opcode, 0, modes[0])?;
+                self.output.push(value);
+            }
+            5 | 6 => {
+                let cond = self.load(opcode, 0, modes[0])?;
+                if (cond != 0) == (opcode % 100 == 5) {
+                    let target = self.load(opcode, 1, modes[1])?;
+                    next_ip = self.check(opcode, target)?;
+                }
+            }
+            9 => {
+                let offset = self.load(opcode, 0, modes[0])?;
+                let rel_base = (self.rel_base as DataType).saturating_add(offset);
+                self.rel_base = self.check(opcode, rel_base)?;
+            }
+            _ => {
+                self.ip = next_ip;
+                self.steps += 1;
+                return Err(Stop::Halted);
+            }
+        }
+
+        self.ip = next_ip;
+        self.steps += 1;
+        Ok(())
+    }
+}
+
+/// xorshift64* generator, deterministic for the given seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Value in range `lo..=hi`
+    fn range(&mut self, lo: DataType, hi: DataType) -> DataType {
+        lo + (self.next() % (hi - lo + 1) as u64) as DataType
+    }
+
+    /// True with probability 1/n
+    fn one_in(&mut self, n: u64) -> bool {
+        self.next().is_multiple_of(n)
+    }
+
+    fn pick<T: Copy>(&mut self, values: &[T]) -> T {
+        values[(self.next() % values.len() as u64) as usize]
+    }
+}
+
+const OPCODES: &[DataType] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+fn arity(op: DataType) -> usize {
+    match op {
+        1 | 2 | 7 | 8 => 3,
+        5 | 6 => 2,
+        3 | 4 | 9 => 1,
+        _ => 0,
+    }
+}
+
+/// Mostly valid program with jumps and writes aimed at its own instructions,
+/// addresses past its end, beyond the memory limit and negative ones, and extreme values
+fn generate(rng: &mut Rng, max_addr: usize) -> (Vec<DataType>, Vec<DataType>) {
+    let count = rng.range(1, 16) as usize;
+    let mut ops: Vec<DataType> = (0..count).map(|_| rng.pick(OPCODES)).collect();
+    if rng.range(0, 3) > 0 {
+        ops[count - 1] = 99;
+    }
+
+    let mut starts = Vec::new();
+    let mut size = 0;
+    for op in &ops {
+        starts.push(size as DataType);
+        size += arity(*op) + 1;
+    }
+    let size = (size + rng.range(0, 4) as usize) as DataType;
+
+    let mut prog = Vec::new();
+    for op in ops {
+        let modes: Vec<DataType> = (0..arity(op))
+            .map(|_| {
+                if rng.one_in(40) {
+                    rng.range(3, 9)
+                } else {
+                    rng.range(0, 2)
+                }
+            })
+            .collect();
+
+        let opcode = if rng.one_in(25) {
+            rng.pick(&[0, -1, 10, 42, 98, 100, 199])
+        } else {
+            modes.iter().rev().fold(0, |acc, mode| acc * 10 + mode) * 100 + op
+        };
+        prog.push(opcode);
+
+        for (idx, mode) in modes.iter().enumerate() {
+            let jump_target = (op == 5 || op == 6) && idx == 1;
+            let value = match *mode {
+                1 if jump_target && rng.range(0, 3) > 0 => rng.pick(&starts),
+                1 if op == 9 => rng.range(-4, 16),
+                1 if rng.one_in(30) => rng.pick(&[DataType::MIN, DataType::MAX, 1 << 40]),
+                1 => rng.range(-50, 50),
+                2 => rng.range(-8, 16),
+                _ if rng.one_in(4) => rng.pick(&starts),
+                _ if rng.one_in(10) => rng.range(-3, -1),
+                _ if rng.one_in(20) => max_addr as DataType + rng.range(1, 10),
+                _ => rng.range(0, size + 8),
+            };
+            prog.push(value);
+        }
+    }
+
+    while (prog.len() as DataType) < size {
+        prog.push(rng.range(-10, 10));
+    }
+
+    let input = (0..rng.range(0, 4)).map(|_| rng.range(-20, 20)).collect();
+
+    (prog, input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_differential() {
+        for decode_cache in &[true, false] {
+            let config = FuzzConfig {
+                decode_cache: *decode_cache,
+                ..FuzzConfig::default()
+            };
+            let report = fuzz(&config);
+
+            if let Some(divergence) = &report.divergence {
+                panic!("{}", divergence);
+            }
+
+            let coverage = report.coverage;
+            assert_eq!(coverage.runs, config.iterations);
+            assert!(coverage.halted > 0 && coverage.waiting > 0 && coverage.step_limit > 0);
+            assert!(coverage.faults > 0 && coverage.overflow > 0);
+            assert!(coverage.negative_relative > 0);
+            assert!(coverage.past_end > 0);
+            assert!(coverage.self_modifying > 0);
+        }
+    }
+
+    #[test]
+    fn test_minimize() {
+        // Loses every output after the first one
+        let buggy = |prog: &[DataType], input: &[DataType], config: &FuzzConfig| {
+            let mut outcome = comp_run(prog, input, config);
+            outcome.output.truncate(1);
+            outcome
+        };
+
+        let divergence = fuzz_with(&FuzzConfig::default(), buggy).divergence.unwrap();
+        let expected = reference_run(&divergence.prog, &divergence.input, &FuzzConfig::default());
+
+        // SET #0; SET rb+0; parameter past the end reads 0
+        assert_eq!(divergence.prog, vec![104, 0, 204]);
+        assert!(divergence.input.is_empty());
+        assert_eq!(divergence.expected, expected);
+        assert_eq!(expected.output.len(), 2);
+        assert_eq!(divergence.actual.output.len(), 1);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::intcode_comp::*;
+use crate::intcode_executor::ExecStatus;
+use crate::log::*;
+use anyhow::{ensure, Result};
+
+#[derive(Clone)]
+struct NodeSpec {
+    name: String,
+    prog: Vec<DataType>,
+    inputs: Vec<DataType>,
+}
+
+/// Describes Intcode machines and connections between them. Output of a node is copied
+/// to every connected node (fan-out), node input can be fed by several nodes (fan-in).
+#[derive(Clone, Default)]
+pub struct GraphBuilder {
+    nodes: Vec<NodeSpec>,
+    links: Vec<Vec<usize>>,
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns node id
+    pub fn add_node<S: Into<String>>(&mut self, name: S, prog: Vec<DataType>) -> usize {
+        self.nodes.push(NodeSpec {
+            name: name.into(),
+            prog,
+            inputs: Vec::new(),
+        });
+        self.links.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    /// Value the node reads before anything sent by other nodes
+    pub fn add_input(&mut self, node: usize, value: DataType) -> Result<()> {
+        self.check_node(node)?;
+        self.nodes[node].inputs.push(value);
+        Ok(())
+    }
+
+    pub fn set_inputs(&mut self, node: usize, values: &[DataType]) -> Result<()> {
+        self.check_node(node)?;
+        self.nodes[node].inputs = values.to_vec();
+        Ok(())
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) -> Result<()> {
+        self.check_node(from)?;
+        self.check_node(to)?;
+        self.links[from].push(to);
+        Ok(())
+    }
+
+    /// Pipeline: every node is connected to the next one
+    pub fn chain(&mut self, nodes: &[usize]) -> Result<()> {
+        for pair in nodes.windows(2) {
+            self.connect(pair[0], pair[1])?;
+        }
+        Ok(())
+    }
+
+    /// Pipeline with the last node connected to the first one
+    pub fn ring(&mut self, nodes: &[usize]) -> Result<()> {
+        self.chain(nodes)?;
+        if let (Some(&last), Some(&first)) = (nodes.last(), nodes.first()) {
+            self.connect(last, first)?;
+        }
+        Ok(())
+    }
+
+    pub fn build<'l>(&self, log: &'l Log) -> Graph<'l> {
+        let comps = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let mut comp = IntcodeComp::new(node.prog.clone(), log);
+                comp.add_input_vec(&mut node.inputs.clone());
+                comp
+            })
+            .collect();
+
+        Graph {
+            names: self.nodes.iter().map(|n| n.name.clone()).collect(),
+            comps,
+            links: self.links.clone(),
+            outputs: vec![Vec::new(); self.nodes.len()],
+        }
+    }
+
+    fn check_node(&self, node: usize) -> Result<()> {
+        ensure!(
+            node < self.nodes.len(),
+            "Unknown node {}. Nodes count: {}",
+            node,
+            self.nodes.len()
+        );
+        Ok(())
+    }
+}
+
+/// Machines built by `GraphBuilder`. Nodes are run one after another in the order
+/// they were added, so the result does not depend on scheduling.
+pub struct Graph<'l> {
+    names: Vec<String>,
+    comps: Vec<IntcodeComp<'l>>,
+    links: Vec<Vec<usize>>,
+    outputs: Vec<Vec<DataType>>,
+}
+
+impl<'l> Graph<'l> {
+    pub fn node(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    pub fn comp(&self, node: usize) -> &IntcodeComp<'l> {
+        &self.comps[node]
+    }
+
+    /// All values produced by the node
+    pub fn outputs(&self, node: usize) -> &[DataType] {
+        &self.outputs[node]
+    }
+
+    pub fn last_output(&self, node: usize) -> Option<DataType> {
+        self.outputs[node].last().cloned()
+    }
+
+    /// Run until all nodes halt, some node is interrupted or none of them can make progress
+    pub fn run(&mut self) -> Result<ExecStatus> {
+        loop {
+            let mut progress = false;
+
+            for node in 0..self.comps.len() {
+                let comp = &mut self.comps[node];
+
+                if comp.is_halted()
+                    || (comp.get_status() == Status::WaitForInput && comp.input().is_empty())
+                {
+                    continue;
+                }
+
+                comp.run()?;
+                progress = true;
+
+                if comp.is_interrupted() {
+                    return Ok(ExecStatus::Interrupted);
+                }
+
+                let output = comp.get_output();
+                for &to in &self.links[node] {
+                    self.comps[to].add_input_vec(&mut output.clone());
+                }
+                self.outputs[node].extend(output);
+            }
+
+            if self.comps.iter().all(|c| c.is_halted()) {
+                return Ok(ExecStatus::Halted);
+            }
+            if !progress {
+                return Ok(ExecStatus::Deadlock);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(prog: &str) -> Vec<DataType> {
+        prog.split(',').map(|v| v.parse().unwrap()).collect()
+    }
+
+    fn amplifiers(prog: &str, phases: &[DataType]) -> Result<(GraphBuilder, Vec<usize>)> {
+        let mut builder = GraphBuilder::new();
+        let mut nodes = Vec::new();
+
+        for (i, phase) in phases.iter().enumerate() {
+            let node = builder.add_node(format!("Amp{}", i), parse(prog));
+            builder.add_input(node, *phase)?;
+            nodes.push(node);
+        }
+        builder.add_input(nodes[0], 0)?;
+
+        Ok((builder, nodes))
+    }
+
+    #[test]
+    fn test_chain() -> Result<()> {
+        let log = Log::new(false);
+        let (mut builder, nodes) = amplifiers(
+            "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0",
+            &[4, 3, 2, 1, 0],
+        )?;
+        builder.chain(&nodes)?;
+
+        let mut graph = builder.build(&log);
+        assert_eq!(graph.run()?, ExecStatus::Halted);
+        assert_eq!(graph.last_output(4), Some(43210));
+        assert_eq!(graph.node("Amp2"), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ring() -> Result<()> {
+        let log = Log::new(false);
+        let prog =
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+        let (mut builder, nodes) = amplifiers(prog, &[9, 8, 7, 6, 5])?;
+        builder.ring(&nodes)?;
+
+        let mut graph = builder.build(&log);
+        assert_eq!(graph.run()?, ExecStatus::Halted);
+        assert_eq!(graph.last_output(4), Some(139_629_729));
+
+        // Without initial signal nobody can start
+        builder.set_inputs(nodes[0], &[9])?;
+        let mut graph = builder.build(&log);
+        assert_eq!(graph.run()?, ExecStatus::Deadlock);
+        assert_eq!(graph.outputs(4), &[] as &[DataType]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dag() -> Result<()> {
+        let log = Log::new(false);
+        let mut builder = GraphBuilder::new();
+
+        let source = builder.add_node("source", vec![104, 5, 99]);
+        let double = builder.add_node("double", parse("3,9,1002,9,2,9,4,9,99,0"));
+        let inc = builder.add_node("inc", parse("3,9,1001,9,1,9,4,9,99,0"));
+        let sum = builder.add_node("sum", parse("3,11,3,12,1,11,12,11,4,11,99,0,0"));
+
+        builder.connect(source, double)?;
+        builder.connect(source, inc)?;
+        builder.connect(double, sum)?;
+        builder.connect(inc, sum)?;
+        assert!(builder.connect(sum, 10).is_err());
+
+        let mut graph = builder.build(&log);
+        assert_eq!(graph.run()?, ExecStatus::Halted);
+        assert_eq!(graph.outputs(sum), &[16]);
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+use crate::intcode_comp::*;
+use crate::intcode_error::*;
+use anyhow::{bail, ensure, Result};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Max number of parameters of the extension opcode
+pub const MAX_EXT_PARAMS: usize = 8;
+
+/// Decoded instruction with the registered extension opcode
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ExtCommand {
+    /// Opcode without parameter modes
+    pub opcode: DataType,
+    pub mnemonic: &'static str,
+    pub arity: usize,
+    modes: [ParamMode; MAX_EXT_PARAMS],
+}
+
+impl ExtCommand {
+    pub fn param_modes(&self) -> &[ParamMode] {
+        &self.modes[..self.arity]
+    }
+}
+
+/// Where the parameter in the custom mode points to
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ModeTarget {
+    /// Memory cell. Instruction reads or writes it like in the position mode.
+    Addr(DataType),
+    /// Value itself. Instruction can not write to it like in the immediate mode.
+    Value(DataType),
+}
+
+pub type OpHandler = Arc<dyn Fn(&mut OpContext<'_, '_>) -> Result<()> + Send + Sync>;
+
+/// Resolves parameter in the custom mode from the raw value stored in the instruction
+pub type ModeResolver = Arc<dyn Fn(&IntcodeComp<'_>, DataType) -> Result<ModeTarget> + Send + Sync>;
+
+#[derive(Clone)]
+struct OpcodeDef {
+    mnemonic: &'static str,
+    arity: usize,
+    handler: OpHandler,
+}
+
+/// Standard instructions plus registered extension opcodes and parameter modes.
+/// Standard opcodes and modes can not be redefined, so programs not using
+/// extensions run the same way.
+#[derive(Clone, Default)]
+pub struct InstructionSet {
+    opcodes: BTreeMap<DataType, OpcodeDef>,
+    modes: BTreeMap<DataType, ModeResolver>,
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register opcode not used by the standard set with `arity` parameters
+    pub fn add_opcode<F>(
+        &mut self,
+        opcode: DataType,
+        mnemonic: &'static str,
+        arity: usize,
+        handler: F,
+    ) -> Result<()>
+    where
+        F: Fn(&mut OpContext<'_, '_>) -> Result<()> + Send + Sync + 'static,
+    {
+        ensure!(
+            (1..100).contains(&opcode),
+            "Opcode {} is out of range 1..99",
+            opcode
+        );
+        ensure!(
+            Command::parse(0, opcode).is_err(),
+            "Opcode {} is a standard one",
+            opcode
+        );
+        ensure!(
+            arity <= MAX_EXT_PARAMS,
+            "Opcode {} has {} parameters. Max: {}",
+            opcode,
+            arity,
+            MAX_EXT_PARAMS
+        );
+        if let Some(def) = self.opcodes.get(&opcode) {
+            bail!(
+                "Opcode {} is already registered as {}",
+                opcode,
+                def.mnemonic
+            );
+        }
+
+        self.opcodes.insert(
+            opcode,
+            OpcodeDef {
+                mnemonic,
+                arity,
+                handler: Arc::new(handler),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Register parameter mode in range 3..=9. It can be used by any instruction.
+    pub fn add_mode<F>(&mut self, mode: DataType, resolver: F) -> Result<()>
+    where
+        F: Fn(&IntcodeComp<'_>, DataType) -> Result<ModeTarget> + Send + Sync + 'static,
+    {
+        ensure!(
+            (3..10).contains(&mode),
+            "Parameter mode {} is out of range 3..9",
+            mode
+        );
+        ensure!(
+            !self.modes.contains_key(&mode),
+            "Parameter mode {} is already registered",
+            mode
+        );
+
+        self.modes.insert(mode, Arc::new(resolver));
+
+        Ok(())
+    }
+
+    /// No extensions are registered
+    pub fn is_standard(&self) -> bool {
+        self.opcodes.is_empty() && self.modes.is_empty()
+    }
+
+    /// Decode opcode located at `ip` into command and number of its parameters
+    pub fn parse(
+        &self,
+        ip: usize,
+        opc: DataType,
+    ) -> std::result::Result<(Command, usize), IntcodeError> {
+        let custom = |mode| self.modes.contains_key(&mode);
+
+        let def = match self.opcodes.get(&(opc % 100)) {
+            Some(def) => def,
+            None => return Command::parse_with(ip, opc, custom),
+        };
+
+        let mut cmd = ExtCommand {
+            opcode: opc % 100,
+            mnemonic: def.mnemonic,
+            arity: def.arity,
+            modes: [ParamMode::Position; MAX_EXT_PARAMS],
+        };
+        let modes = ParamMode::parse_with(ip, opc, def.arity as u8, custom)?;
+        cmd.modes[..def.arity].copy_from_slice(&modes);
+
+        Ok((Command::Ext(cmd), def.arity))
+    }
+
+    pub(crate) fn handler(&self, opcode: DataType) -> Option<OpHandler> {
+        self.opcodes.get(&opcode).map(|def| def.handler.clone())
+    }
+
+    pub(crate) fn resolver(&self, mode: DataType) -> Option<ModeResolver> {
+        self.modes.get(&mode).cloned()
+    }
+}
+
+/// Machine access for the extension opcode handler
+pub struct OpContext<'a, 'l> {
+    comp: &'a mut IntcodeComp<'l>,
+    cmd: ExtCommand,
+    next_ip: usize,
+    halted: bool,
+}
+
+impl<'a, 'l> OpContext<'a, 'l> {
+    pub(crate) fn new(comp: &'a mut IntcodeComp<'l>, cmd: ExtCommand, next_ip: usize) -> Self {
+        Self {
+            comp,
+            cmd,
+            next_ip,
+            halted: false,
+        }
+    }
+
+    pub fn comp(&self) -> &IntcodeComp<'l> {
+        self.comp
+    }
+
+    pub fn command(&self) -> ExtCommand {
+        self.cmd
+    }
+
+    /// Value of the parameter `index` (starting from 0) according to its mode
+    pub fn param(&mut self, index: usize) -> Result<DataType> {
+        let mode = self.mode(index)?;
+        self.comp.get_param_value(index + 1, mode)
+    }
+
+    pub fn set_param(&mut self, index: usize, value: DataType) -> Result<()> {
+        let mode = self.mode(index)?;
+        self.comp.set_param_value(index + 1, mode, value)
+    }
+
+    pub fn set_mem(&mut self, addr: usize, value: DataType) {
+        self.comp.set_mem(addr, value);
+    }
+
+    /// Next queued input value
+    pub fn input(&mut self) -> Option<DataType> {
+        self.comp.take_input()
+    }
+
+    pub fn output(&mut self, value: DataType) {
+        self.comp.push_output(value);
+    }
+
+    /// Continue execution from `addr` instead of the next instruction
+    pub fn jump(&mut self, addr: DataType) -> Result<()> {
+        self.next_ip = self.comp.check_addr(addr)?;
+        Ok(())
+    }
+
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    pub(crate) fn result(&self) -> (usize, bool) {
+        (self.next_ip, self.halted)
+    }
+
+    fn mode(&self, index: usize) -> Result<ParamMode> {
+        match self.cmd.param_modes().get(index) {
+            Some(mode) => Ok(*mode),
+            None => bail!(
+                "{} has no parameter {}. Parameters count: {}",
+                self.cmd.mnemonic,
+                index,
+                self.cmd.arity
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+    use std::sync::Mutex;
+
+    fn isa() -> Result<InstructionSet> {
+        let mut isa = InstructionSet::new();
+
+        // Swap two memory cells
+        isa.add_opcode(20, "SWAP", 2, |ctx| {
+            let (a, b) = (ctx.param(0)?, ctx.param(1)?);
+            ctx.set_param(0, b)?;
+            ctx.set_param(1, a)
+        })?;
+        // Output all queued input in reverse order and halt
+        isa.add_opcode(21, "REV", 0, |ctx| {
+            let mut values = Vec::new();
+            while let Some(value) = ctx.input() {
+                values.push(value);
+            }
+            values.into_iter().rev().for_each(|v| ctx.output(v));
+            ctx.halt();
+            Ok(())
+        })?;
+        // Indirect: address of the cell is stored at the parameter address
+        isa.add_mode(3, |comp, raw| {
+            ensure!(raw >= 0, "Negative address {}", raw);
+            Ok(ModeTarget::Addr(comp.get_mem(raw as usize)))
+        })?;
+
+        Ok(isa)
+    }
+
+    #[test]
+    fn test_extensions() -> Result<()> {
+        let log = Log::new(false);
+
+        // SWAP [9], [10]; SET <[11]>; REV; data: 7, 8, 10
+        let prog = vec![20, 9, 10, 304, 11, 21, 99, 0, 0, 7, 8, 10];
+        let mut comp = IntcodeComp::new(prog.clone(), &log);
+        comp.set_instruction_set(isa()?);
+        comp.add_input_vec(&mut vec![1, 2, 3]);
+        comp.exec()?;
+
+        assert_eq!(comp.get_output(), vec![7, 3, 2, 1]);
+        assert_eq!((comp.get_mem(9), comp.get_mem(10)), (8, 7));
+        assert_eq!(comp.get_ip(), 6);
+
+        // Standard set rejects both the opcode and the mode
+        let mut comp = IntcodeComp::new(prog, &log);
+        assert_eq!(
+            comp.exec().unwrap_err().downcast::<IntcodeError>()?,
+            IntcodeError::InvalidOpcode { ip: 0, opcode: 20 }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump() -> Result<()> {
+        let log = Log::new(false);
+        let dumps = Arc::new(Mutex::new(Vec::new()));
+        let mut isa = InstructionSet::new();
+
+        let sink = dumps.clone();
+        isa.add_opcode(42, "DUMP", 1, move |ctx| {
+            let value = ctx.param(0)?;
+            let comp = ctx.comp();
+            sink.lock()
+                .unwrap()
+                .push((comp.get_ip(), comp.get_rel_base(), value));
+            Ok(())
+        })?;
+        // Jump to the address in the first parameter if the second one is odd
+        isa.add_opcode(43, "JODD", 2, |ctx| {
+            let (target, value) = (ctx.param(0)?, ctx.param(1)?);
+            if value % 2 != 0 {
+                ctx.jump(target)?;
+            }
+            Ok(())
+        })?;
+
+        // ARB 5; DUMP #3; JODD #11, #3; EXIT; DUMP [0]; EXIT
+        let prog = vec![109, 5, 142, 3, 1143, 11, 3, 99, 0, 0, 0, 42, 0, 99];
+        let mut comp = IntcodeComp::new(prog, &log);
+        comp.set_instruction_set(isa.clone());
+        comp.exec()?;
+
+        assert_eq!(*dumps.lock().unwrap(), vec![(2, 5, 3), (11, 5, 109)]);
+        assert!(comp.instruction_set().parse(0, 142).is_ok());
+
+        assert!(isa.add_opcode(1, "ADD2", 3, |_| Ok(())).is_err());
+        assert!(isa.add_opcode(42, "DUMP2", 1, |_| Ok(())).is_err());
+        assert!(isa
+            .add_mode(2, |_, raw| Ok(ModeTarget::Value(raw)))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use crate::intcode_comp::*;
+use crate::intcode_snapshot::*;
+use std::collections::VecDeque;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct JournalConfig {
+    /// Max number of instructions that can be undone one by one
+    pub max_entries: usize,
+    /// Take full snapshot every N instructions
+    pub checkpoint_interval: u64,
+    pub max_checkpoints: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 100_000,
+            checkpoint_interval: 10_000,
+            max_checkpoints: 16,
+        }
+    }
+}
+
+/// Data needed to undo one executed instruction
+#[derive(Debug, PartialEq, Clone)]
+pub struct JournalEntry {
+    pub step: u64,
+    pub ip: usize,
+    pub rel_base: usize,
+    pub status: Status,
+    pub mem_size: usize,
+    /// Address and value before the write
+    pub writes: Vec<(usize, DataType)>,
+    pub input: Option<DataType>,
+    /// Output queue length before the instruction, counting values already taken by the host
+    pub output_len: usize,
+}
+
+impl JournalEntry {
+    pub fn new(
+        step: u64,
+        ip: usize,
+        rel_base: usize,
+        status: Status,
+        mem_size: usize,
+        output_len: usize,
+    ) -> Self {
+        Self {
+            step,
+            ip,
+            rel_base,
+            status,
+            mem_size,
+            writes: Vec::new(),
+            input: None,
+            output_len,
+        }
+    }
+}
+
+/// Bounded history of executed instructions plus periodic snapshots.
+/// Going back within the entries is done by undoing them, going further back
+/// restores the nearest checkpoint and re-runs the program with the recorded input.
+#[derive(Debug, Clone)]
+pub struct Journal {
+    config: JournalConfig,
+    entries: VecDeque<JournalEntry>,
+    checkpoints: VecDeque<Snapshot>,
+    /// Consumed input values since the oldest checkpoint
+    inputs: VecDeque<(u64, DataType)>,
+}
+
+impl Journal {
+    pub fn new(config: JournalConfig) -> Self {
+        Self {
+            config,
+            entries: VecDeque::new(),
+            checkpoints: VecDeque::new(),
+            inputs: VecDeque::new(),
+        }
+    }
+
+    pub fn config(&self) -> &JournalConfig {
+        &self.config
+    }
+
+    pub fn entries(&self) -> &VecDeque<JournalEntry> {
+        &self.entries
+    }
+
+    pub fn checkpoints(&self) -> &VecDeque<Snapshot> {
+        &self.checkpoints
+    }
+
+    /// Earliest step that can be reached
+    pub fn earliest_step(&self) -> Option<u64> {
+        let entry = self.entries.front().map(|e| e.step);
+        let checkpoint = self.checkpoints.front().map(|c| c.steps);
+
+        match (entry, checkpoint) {
+            (Some(e), Some(c)) => Some(e.min(c)),
+            (e, c) => e.or(c),
+        }
+    }
+
+    pub(crate) fn need_checkpoint(&self, step: u64) -> bool {
+        self.config.checkpoint_interval > 0
+            && step.is_multiple_of(self.config.checkpoint_interval)
+            && self.checkpoints.back().map(|c| c.steps) != Some(step)
+    }
+
+    pub(crate) fn add_checkpoint(&mut self, snapshot: Snapshot) {
+        self.checkpoints.push_back(snapshot);
+
+        while self.checkpoints.len() > self.config.max_checkpoints.max(1) {
+            self.checkpoints.pop_front();
+        }
+
+        let oldest = self.checkpoints.front().map(|c| c.steps).unwrap_or(0);
+        while self.inputs.front().map(|i| i.0 < oldest).unwrap_or(false) {
+            self.inputs.pop_front();
+        }
+    }
+
+    pub(crate) fn push(&mut self, entry: JournalEntry) {
+        if let Some(input) = entry.input {
+            self.inputs.push_back((entry.step, input));
+        }
+
+        self.entries.push_back(entry);
+
+        while self.entries.len() > self.config.max_entries {
+            self.entries.pop_front();
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<JournalEntry> {
+        let entry = self.entries.pop_back()?;
+
+        if entry.input.is_some() {
+            self.inputs.pop_back();
+        }
+        if self
+            .checkpoints
+            .back()
+            .map(|c| c.steps > entry.step)
+            .unwrap_or(false)
+        {
+            self.checkpoints.pop_back();
+        }
+
+        Some(entry)
+    }
+
+    pub(crate) fn checkpoint_before(&self, step: u64) -> Option<&Snapshot> {
+        self.checkpoints.iter().rev().find(|c| c.steps <= step)
+    }
+
+    /// Forget everything recorded at or after given step and return input
+    /// values consumed since then
+    pub(crate) fn rewind(&mut self, step: u64) -> Vec<DataType> {
+        while self.entries.back().map(|e| e.step >= step).unwrap_or(false) {
+            self.entries.pop_back();
+        }
+        while self
+            .checkpoints
+            .back()
+            .map(|c| c.steps > step)
+            .unwrap_or(false)
+        {
+            self.checkpoints.pop_back();
+        }
+
+        let pos = self
+            .inputs
+            .iter()
+            .position(|i| i.0 >= step)
+            .unwrap_or(self.inputs.len());
+
+        self.inputs.drain(pos..).map(|i| i.1).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+    use anyhow::Result;
+
+    // Outputs running sum of input values
+    const PROG: [DataType; 11] = [3, 12, 1, 12, 11, 11, 4, 11, 1105, 1, 0];
+
+    #[test]
+    fn test_step_back() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+
+        comp.start_recording(JournalConfig::default());
+        comp.add_input_vec(&mut vec![5, 7]);
+        comp.run()?;
+
+        let end = comp.snapshot();
+        assert_eq!(comp.output(), &[5, 12]);
+        assert_eq!(comp.get_steps(), 8);
+
+        assert!(comp.step_back()?);
+        assert_eq!(comp.get_ip(), 8);
+        assert!(comp.step_back()?);
+        assert_eq!(comp.get_ip(), 6);
+        assert_eq!(comp.output(), &[5]);
+
+        comp.goto_step(2)?;
+        assert_eq!(comp.get_ip(), 6);
+        assert_eq!(comp.get_mem(12), 5);
+        assert_eq!(comp.input(), &[7]);
+        assert_eq!(comp.output(), &[] as &[DataType]);
+
+        comp.goto_step(0)?;
+        assert_eq!(comp.input(), &[5, 7]);
+        assert!(!comp.step_back()?);
+
+        comp.run()?;
+        assert_eq!(comp.snapshot(), end);
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_back_taken_output() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+
+        comp.start_recording(JournalConfig::default());
+        comp.add_input_vec(&mut vec![5, 7]);
+        assert_eq!(comp.run_until_output()?, Some(5));
+        comp.run()?;
+        assert_eq!(comp.output(), &[12]);
+
+        // 12 is still queued and removed, 5 was taken and stays taken
+        comp.goto_step(4)?;
+        assert_eq!(comp.output(), &[] as &[DataType]);
+        comp.goto_step(2)?;
+        assert_eq!(comp.output(), &[] as &[DataType]);
+
+        comp.run()?;
+        assert_eq!(comp.get_output(), vec![5, 12]);
+        comp.step_back()?;
+        comp.step_back()?;
+        assert_eq!(comp.output(), &[] as &[DataType]);
+        comp.run()?;
+        assert_eq!(comp.output(), &[12]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_checkpoints() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+
+        comp.start_recording(JournalConfig {
+            max_entries: 3,
+            checkpoint_interval: 4,
+            max_checkpoints: 10,
+        });
+        comp.add_input_vec(&mut vec![1, 2, 3]);
+        comp.run()?;
+        assert_eq!(comp.get_output(), vec![1, 3, 6]);
+        assert_eq!(comp.get_steps(), 12);
+
+        let mut expected = IntcodeComp::new(PROG.to_vec(), &log);
+        expected.add_input_vec(&mut vec![1, 2, 3]);
+        for _ in 0..6 {
+            expected.step()?;
+        }
+
+        // Out of entries range: restored from checkpoint at step 4 and replayed
+        comp.goto_step(6)?;
+        assert_eq!(comp.get_ip(), expected.get_ip());
+        assert_eq!(comp.get_mem(11), expected.get_mem(11));
+        assert_eq!(comp.input(), expected.input());
+        assert_eq!(comp.get_steps(), 6);
+
+        // Forward again. Output queue is restored from the checkpoint.
+        comp.goto_step(12)?;
+        assert_eq!(comp.get_mem(11), 6);
+        assert_eq!(comp.output(), &[1, 3, 6]);
+        Ok(())
+    }
+}
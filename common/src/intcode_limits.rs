@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Cancellation token and wall clock are checked once per this number of instructions
+pub const CHECK_INTERVAL: u64 = 1024;
+
+/// Reason the program was stopped before it halted or asked for input
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum Interrupt {
+    InstructionLimit,
+    Timeout,
+    Cancelled,
+}
+
+/// Shared flag stopping every machine it is attached to. Clones share the flag.
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Execution limits of the `IntcodeComp`
+#[derive(Debug, Default, Clone)]
+pub struct Limits {
+    /// Max number of instructions executed since the program start
+    pub max_steps: Option<u64>,
+    /// Max wall clock time of a single run call
+    pub timeout: Option<Duration>,
+    pub cancel: Option<CancelToken>,
+}
+
+impl Limits {
+    pub fn deadline(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| Instant::now() + timeout)
+    }
+
+    /// Check limits before the next instruction. `count` is the number of instructions
+    /// executed by the current call.
+    pub fn check(&self, steps: u64, count: u64, deadline: Option<Instant>) -> Option<Interrupt> {
+        if self.max_steps.is_some_and(|max| steps >= max) {
+            return Some(Interrupt::InstructionLimit);
+        }
+
+        if !count.is_multiple_of(CHECK_INTERVAL) {
+            return None;
+        }
+
+        if self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+            Some(Interrupt::Cancelled)
+        } else if deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(Interrupt::Timeout)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_comp::*;
+    use crate::intcode_device::*;
+    use crate::intcode_error::*;
+    use crate::log::*;
+    use anyhow::Result;
+
+    // Counts up forever: ADD [7], #1, [7]; JMP #0
+    const LOOP: [DataType; 8] = [1001, 7, 1, 7, 1105, 1, 0, 0];
+
+    #[test]
+    fn test_instruction_limit() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(LOOP.to_vec(), &log);
+
+        comp.set_instruction_limit(Some(10));
+        comp.run()?;
+        assert_eq!(
+            comp.get_status(),
+            Status::Interrupted(Interrupt::InstructionLimit)
+        );
+        assert_eq!(comp.get_steps(), 10);
+        assert_eq!(comp.get_mem(7), 5);
+
+        // Nothing is executed until the budget is raised
+        comp.run()?;
+        assert_eq!(comp.get_steps(), 10);
+        assert!(!comp.step()?);
+
+        comp.set_instruction_limit(Some(20));
+        comp.run()?;
+        assert_eq!(comp.get_steps(), 20);
+        assert_eq!(comp.get_mem(7), 10);
+
+        comp.restart();
+        let err = comp.exec().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<IntcodeError>(),
+            Some(&IntcodeError::InstructionLimit {
+                ip: 0,
+                opcode: 1001,
+                limit: 20
+            })
+        );
+
+        // Interrupted program is resumed by exec
+        let mut comp = IntcodeComp::new(vec![104, 1, 104, 2, 99], &log);
+        comp.set_instruction_limit(Some(1));
+        assert!(comp.exec().is_err());
+        assert_eq!(comp.get_output(), vec![1]);
+        comp.set_instruction_limit(None);
+        comp.exec()?;
+        assert_eq!(comp.get_output(), vec![2]);
+        assert!(comp.is_halted());
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeout() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(LOOP.to_vec(), &log);
+
+        comp.set_timeout(Some(Duration::from_millis(20)));
+        comp.run()?;
+        assert_eq!(comp.get_status(), Status::Interrupted(Interrupt::Timeout));
+        assert!(comp.get_steps() > 0);
+
+        // Every run call gets its own time
+        let steps = comp.get_steps();
+        comp.run_device(&mut VecDevice::new(Vec::new()))?;
+        assert_eq!(comp.get_status(), Status::Interrupted(Interrupt::Timeout));
+        assert!(comp.get_steps() > steps);
+
+        let err = comp.exec().unwrap_err().downcast::<IntcodeError>()?;
+        assert_eq!(
+            err,
+            IntcodeError::Timeout {
+                ip: err.ip(),
+                opcode: err.opcode(),
+                timeout: Duration::from_millis(20)
+            }
+        );
+        assert!(err.to_string().starts_with("Timeout 20ms is reached."));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel() -> Result<()> {
+        let log = Log::new(false);
+        let token = CancelToken::new();
+        let mut comp = IntcodeComp::new(LOOP.to_vec(), &log);
+        comp.set_cancel_token(Some(token.clone()));
+
+        let status = std::thread::scope(|s| {
+            let handle = s.spawn(|| -> Result<Status> {
+                comp.run()?;
+                Ok(comp.get_status())
+            });
+            std::thread::sleep(Duration::from_millis(10));
+            token.cancel();
+            handle.join().unwrap()
+        })?;
+        assert_eq!(status, Status::Interrupted(Interrupt::Cancelled));
+        assert_eq!(
+            comp.exec().unwrap_err().downcast::<IntcodeError>()?,
+            IntcodeError::Cancelled {
+                ip: comp.get_ip(),
+                opcode: comp.get_mem(comp.get_ip())
+            }
+        );
+
+        // Programs that finish in time are not affected
+        let mut comp = IntcodeComp::new(vec![104, 1, 99], &log);
+        comp.set_instruction_limit(Some(2));
+        comp.set_timeout(Some(Duration::from_secs(60)));
+        comp.exec()?;
+        assert_eq!(comp.get_output(), vec![1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+//! Program text format: values separated by commas and/or whitespace (newlines
+//! included), a trailing comma is allowed, `;` or `#` starts a comment up to the end
+//! of the line.
+
+use crate::intcode_comp::DataType;
+use anyhow::{anyhow, Result};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Path that reads the program from stdin
+pub const STDIN_PATH: &str = "-";
+
+/// Value that can't be parsed. Missing value between two commas has empty text.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ProgError {
+    /// Index of the value in the program
+    pub index: usize,
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for ProgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Wrong program value #{} '{}' at line {}.",
+            self.index, self.text, self.line
+        )
+    }
+}
+
+impl Error for ProgError {}
+
+pub fn parse_prog(data: &str) -> Result<Vec<DataType>> {
+    parse_values(data)
+}
+
+/// Program with values of any type, e.g. `BigInt`
+pub fn parse_values<T: FromStr>(data: &str) -> Result<Vec<T>> {
+    let mut values = Vec::new();
+    // Value was seen since the last comma
+    let mut separated = false;
+
+    for (line_no, line) in data.lines().enumerate() {
+        let line = line.split(&[';', '#'][..]).next().unwrap_or("");
+
+        for (n, part) in line.split(',').enumerate() {
+            if n > 0 {
+                if !separated {
+                    return Err(error(values.len(), line_no, ""));
+                }
+                separated = false;
+            }
+
+            for text in part.split_whitespace() {
+                let value = text
+                    .parse()
+                    .map_err(|_| error(values.len(), line_no, text))?;
+                values.push(value);
+                separated = true;
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+fn error(index: usize, line_no: usize, text: &str) -> anyhow::Error {
+    ProgError {
+        index,
+        line: line_no + 1,
+        text: text.to_string(),
+    }
+    .into()
+}
+
+pub fn read_prog<R: Read>(reader: R) -> Result<Vec<DataType>> {
+    parse_prog(&read_text(reader)?)
+}
+
+/// Program from the file, or from stdin for `STDIN_PATH`
+pub fn read_prog_file<P: AsRef<Path>>(path: P) -> Result<Vec<DataType>> {
+    parse_prog(&read_prog_text(path)?)
+}
+
+/// Unparsed program text from the file, or from stdin for `STDIN_PATH`
+pub fn read_prog_text<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+
+    if path == Path::new(STDIN_PATH) {
+        read_text(io::stdin().lock())
+    } else {
+        fs::read_to_string(path).map_err(|err| anyhow!("{}: {}", path.display(), err))
+    }
+}
+
+fn read_text<R: Read>(mut reader: R) -> Result<String> {
+    let mut data = String::new();
+    reader.read_to_string(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prog_error(data: &str) -> ProgError {
+        parse_prog(data)
+            .unwrap_err()
+            .downcast::<ProgError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_formats() -> Result<()> {
+        let expected = vec![1, 0, -3, 99];
+        assert_eq!(parse_prog("1,0,-3,99")?, expected);
+        assert_eq!(parse_prog(" 1, 0 ,-3,99,\n")?, expected);
+        assert_eq!(parse_prog("1\n0\r\n-3\n99\n")?, expected);
+        assert_eq!(parse_prog("1 0\t-3 99")?, expected);
+        assert_eq!(
+            parse_prog("; header\n1,0,  # ADD\n\n-3,\n99 ; EXIT\n")?,
+            expected
+        );
+        assert!(parse_prog("")?.is_empty());
+        assert!(parse_prog(" \n; nothing\n")?.is_empty());
+
+        assert_eq!(read_prog(&b"104,7,99\n"[..])?, vec![104, 7, 99]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            prog_error("1,2,x3,4"),
+            ProgError {
+                index: 2,
+                line: 1,
+                text: "x3".to_string()
+            }
+        );
+        assert_eq!(
+            prog_error("1,2\n3,,4").to_string(),
+            "Wrong program value #3 '' at line 2."
+        );
+        assert_eq!(prog_error(",1").index, 0);
+        assert_eq!(
+            prog_error("1,99999999999999999999").text,
+            "99999999999999999999"
+        );
+        assert!(read_prog_file("no_such_file.txt")
+            .unwrap_err()
+            .to_string()
+            .starts_with("no_such_file.txt: "));
+    }
+}
@@ -0,0 +1,417 @@
+use crate::intcode_comp::DataType;
+use anyhow::{anyhow, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Highest address the program can access unless changed with `IntcodeComp::set_max_addr`
+pub const DEFAULT_MAX_ADDR: usize = (1 << 24) - 1;
+
+/// Storage for the `IntcodeComp` memory. Cells that were never written are zeros.
+pub trait Memory: Send {
+    fn get(&self, addr: usize) -> DataType;
+
+    fn set(&mut self, addr: usize, value: DataType);
+
+    /// Highest written address + 1
+    fn size(&self) -> usize;
+
+    /// Forget (zero) all cells starting from `size`
+    fn truncate(&mut self, size: usize);
+
+    /// Replace whole content
+    fn load(&mut self, data: &[DataType]);
+
+    /// Dense copy of all `size` cells. Use `image` for memory with far writes.
+    fn to_vec(&self) -> Vec<DataType> {
+        (0..self.size()).map(|addr| self.get(addr)).collect()
+    }
+
+    /// Sparse copy of the content
+    fn image(&self) -> MemoryImage {
+        MemoryImage::from_vec(self.to_vec())
+    }
+
+    /// Memory allocates only written parts, so sparse images can be loaded into it
+    fn is_sparse(&self) -> bool {
+        false
+    }
+
+    /// Replace whole content with the image
+    fn load_image(&mut self, image: &MemoryImage) {
+        self.load(&[]);
+        for (addr, value) in image.cells().filter(|(_, value)| *value != 0) {
+            self.set(addr, value);
+        }
+        if image.size() > self.size() {
+            self.set(image.size() - 1, 0);
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Memory>;
+}
+
+impl Clone for Box<dyn Memory> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Memory content as segments of cells in address order. Cells outside the
+/// segments are zeros.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "ImageData")]
+pub struct MemoryImage {
+    size: usize,
+    segments: Vec<(usize, Vec<DataType>)>,
+}
+
+#[derive(Deserialize)]
+struct ImageData {
+    size: usize,
+    segments: Vec<(usize, Vec<DataType>)>,
+}
+
+impl TryFrom<ImageData> for MemoryImage {
+    type Error = anyhow::Error;
+
+    fn try_from(data: ImageData) -> Result<Self> {
+        Self::from_segments(data.size, data.segments)
+    }
+}
+
+impl MemoryImage {
+    pub fn from_vec(data: Vec<DataType>) -> Self {
+        let size = data.len();
+        let segments = if data.is_empty() {
+            Vec::new()
+        } else {
+            vec![(0, data)]
+        };
+
+        Self { size, segments }
+    }
+
+    /// Segments must be sorted by start address, not overlap and end within `size`
+    pub fn from_segments(size: usize, segments: Vec<(usize, Vec<DataType>)>) -> Result<Self> {
+        let mut end = 0;
+        for (start, cells) in &segments {
+            ensure!(
+                *start >= end,
+                "Memory segment at {} overlaps previous one.",
+                start
+            );
+            end = start
+                .checked_add(cells.len())
+                .ok_or_else(|| anyhow!("Memory segment at {} is too long.", start))?;
+        }
+        ensure!(
+            end <= size,
+            "Memory segments end at {} beyond memory size {}.",
+            end,
+            size
+        );
+
+        Ok(Self { size, segments })
+    }
+
+    /// Highest written address + 1
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Start address and cells of every segment
+    pub fn segments(&self) -> &[(usize, Vec<DataType>)] {
+        &self.segments
+    }
+
+    pub fn get(&self, addr: usize) -> DataType {
+        let idx = self.segments.partition_point(|(start, _)| *start <= addr);
+        idx.checked_sub(1)
+            .and_then(|idx| {
+                let (start, cells) = &self.segments[idx];
+                cells.get(addr - start).copied()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Stored cells cover at least half of the memory
+    pub fn is_dense(&self) -> bool {
+        let stored: usize = self.segments.iter().map(|(_, cells)| cells.len()).sum();
+        stored >= self.size / 2
+    }
+
+    /// Stored cells in address order
+    pub fn cells(&self) -> impl Iterator<Item = (usize, DataType)> + '_ {
+        self.segments.iter().flat_map(|(start, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .map(move |(offset, value)| (start + offset, *value))
+        })
+    }
+
+    /// Dense copy of all `size` cells
+    pub fn to_vec(&self) -> Vec<DataType> {
+        let mut data = vec![0; self.size];
+        for (addr, value) in self.cells() {
+            data[addr] = value;
+        }
+        data
+    }
+}
+
+/// Contiguous memory growing up to the highest written address
+#[derive(Clone, Default)]
+pub struct FlatMemory {
+    cells: Vec<DataType>,
+}
+
+impl FlatMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Memory for FlatMemory {
+    fn get(&self, addr: usize) -> DataType {
+        self.cells.get(addr).cloned().unwrap_or(0)
+    }
+
+    fn set(&mut self, addr: usize, value: DataType) {
+        if addr >= self.cells.len() {
+            self.cells.resize(addr + 1, 0);
+        }
+        self.cells[addr] = value;
+    }
+
+    fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn truncate(&mut self, size: usize) {
+        self.cells.truncate(size);
+    }
+
+    fn load(&mut self, data: &[DataType]) {
+        self.cells = data.to_vec();
+    }
+
+    fn to_vec(&self) -> Vec<DataType> {
+        self.cells.clone()
+    }
+
+    fn load_image(&mut self, image: &MemoryImage) {
+        self.cells = image.to_vec();
+    }
+
+    fn box_clone(&self) -> Box<dyn Memory> {
+        Box::new(self.clone())
+    }
+}
+
+/// Sparse memory allocated by pages on first write
+#[derive(Clone)]
+pub struct PagedMemory {
+    page_size: usize,
+    pages: HashMap<usize, Vec<DataType>>,
+    size: usize,
+}
+
+impl PagedMemory {
+    pub const DEFAULT_PAGE_SIZE: usize = 1024;
+
+    pub fn new() -> Self {
+        Self::with_page_size(Self::DEFAULT_PAGE_SIZE)
+    }
+
+    pub fn with_page_size(page_size: usize) -> Self {
+        Self {
+            page_size: page_size.max(1),
+            pages: HashMap::new(),
+            size: 0,
+        }
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl Default for PagedMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory for PagedMemory {
+    fn get(&self, addr: usize) -> DataType {
+        self.pages
+            .get(&(addr / self.page_size))
+            .map(|page| page[addr % self.page_size])
+            .unwrap_or(0)
+    }
+
+    fn set(&mut self, addr: usize, value: DataType) {
+        let page_size = self.page_size;
+        let page = self
+            .pages
+            .entry(addr / page_size)
+            .or_insert_with(|| vec![0; page_size]);
+
+        page[addr % page_size] = value;
+        self.size = self.size.max(addr + 1);
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn truncate(&mut self, size: usize) {
+        if size >= self.size {
+            return;
+        }
+
+        let page_size = self.page_size;
+        self.pages.retain(|idx, _| idx * page_size < size);
+        if let Some(page) = self.pages.get_mut(&(size / page_size)) {
+            page[size % page_size..].iter_mut().for_each(|v| *v = 0);
+        }
+        self.size = size;
+    }
+
+    fn load(&mut self, data: &[DataType]) {
+        self.pages.clear();
+        self.size = 0;
+
+        for (addr, value) in data.iter().enumerate() {
+            self.set(addr, *value);
+        }
+    }
+
+    fn is_sparse(&self) -> bool {
+        true
+    }
+
+    /// Allocated pages only, adjacent pages are joined
+    fn image(&self) -> MemoryImage {
+        let mut indexes: Vec<usize> = self.pages.keys().copied().collect();
+        indexes.sort_unstable();
+
+        let mut segments: Vec<(usize, Vec<DataType>)> = Vec::new();
+        for idx in indexes {
+            let start = idx * self.page_size;
+            let cells = &self.pages[&idx][..self.page_size.min(self.size - start)];
+
+            match segments.last_mut() {
+                Some((prev, prev_cells)) if *prev + prev_cells.len() == start => {
+                    prev_cells.extend_from_slice(cells)
+                }
+                _ => segments.push((start, cells.to_vec())),
+            }
+        }
+
+        MemoryImage {
+            size: self.size,
+            segments,
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn Memory> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_comp::*;
+    use crate::log::*;
+
+    #[test]
+    fn test_paged() {
+        let mut mem = PagedMemory::with_page_size(4);
+
+        mem.load(&[1, 2, 3, 4, 5]);
+        mem.set(1_000_000_000, 7);
+
+        assert_eq!(mem.page_count(), 3);
+        assert_eq!(mem.get(4), 5);
+        assert_eq!(mem.get(1_000_000_000), 7);
+        assert_eq!(mem.get(999), 0);
+        assert_eq!(mem.size(), 1_000_000_001);
+
+        let image = mem.image();
+        assert_eq!(image.size(), 1_000_000_001);
+        assert_eq!(image.segments().len(), 2);
+        assert_eq!(image.segments()[0], (0, vec![1, 2, 3, 4, 5, 0, 0, 0]));
+        assert_eq!(image.get(1_000_000_000), 7);
+        assert_eq!(image.get(6), 0);
+        assert_eq!(image.get(2_000_000_000), 0);
+        assert_eq!(image.cells().count(), 9);
+        assert!(!image.is_dense());
+        assert!(MemoryImage::from_vec(vec![1, 2]).is_dense());
+
+        let mut copy = PagedMemory::with_page_size(4);
+        copy.load_image(&image);
+        assert_eq!(copy.size(), 1_000_000_001);
+        assert_eq!(copy.page_count(), 3);
+        assert_eq!(copy.get(1_000_000_000), 7);
+
+        mem.truncate(3);
+        assert_eq!(mem.to_vec(), vec![1, 2, 3]);
+        assert_eq!(mem.page_count(), 1);
+        assert_eq!(mem.image(), MemoryImage::from_vec(vec![1, 2, 3]));
+
+        assert!(MemoryImage::from_segments(10, vec![(0, vec![1; 4]), (3, vec![2])]).is_err());
+        assert!(MemoryImage::from_segments(4, vec![(2, vec![1; 3])]).is_err());
+        assert!(
+            serde_json::from_str::<MemoryImage>(r#"{"size":1,"segments":[[0,[1,2]]]}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_limits() -> Result<()> {
+        let log = Log::new(false);
+
+        // Write far away with paged memory
+        let mut comp =
+            IntcodeComp::new(vec![1101, 3, 4, 1_000_000_000, 4, 1_000_000_000, 99], &log);
+        comp.set_memory(Box::new(PagedMemory::new()));
+        comp.set_max_addr(usize::MAX);
+        comp.exec()?;
+        assert_eq!(comp.get_output(), vec![7]);
+
+        // Copies of the state keep only written pages
+        let snapshot = comp.snapshot();
+        assert_eq!(snapshot.memory.size(), 1_000_000_001);
+        assert_eq!(snapshot.memory.segments().len(), 2);
+        comp.set_memory(Box::new(PagedMemory::with_page_size(16)));
+        comp.restore(snapshot.clone())?;
+        assert_eq!(comp.get_mem(1_000_000_000), 7);
+        assert_eq!(comp.snapshot().memory.cells().count(), 17);
+
+        // Flat memory is replaced instead of allocating all cells
+        let err = IntcodeComp::from_snapshot(snapshot.clone(), &log)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("exceeds memory limit"), "{}", err);
+        let mut copy = IntcodeComp::new(Vec::new(), &log);
+        copy.set_max_addr(usize::MAX);
+        copy.restore(snapshot)?;
+        assert_eq!(copy.get_mem(1_000_000_000), 7);
+        assert_eq!(copy.mem_size(), 1_000_000_001);
+
+        // Same program with default limit
+        let mut comp =
+            IntcodeComp::new(vec![1101, 3, 4, 1_000_000_000, 4, 1_000_000_000, 99], &log);
+        let err = comp.exec().unwrap_err().to_string();
+        assert!(err.contains("exceeds memory limit"), "{}", err);
+
+        // Negative relative address
+        let mut comp = IntcodeComp::new(vec![204, -1, 99], &log);
+        let err = comp.exec().unwrap_err().to_string();
+        assert!(err.contains("Negative address -1"), "{}", err);
+        Ok(())
+    }
+}
@@ -0,0 +1,462 @@
+use crate::intcode_comp::*;
+use crate::log::*;
+use anyhow::{bail, ensure, Result};
+use std::collections::{BTreeMap, HashMap};
+
+/// Packet layout in the node output: destination address followed by the payload
+#[derive(Debug, PartialEq, Clone)]
+pub struct PacketSchema {
+    pub payload_size: usize,
+    /// Input value for nodes without incoming packets
+    pub no_data: DataType,
+}
+
+impl Default for PacketSchema {
+    fn default() -> Self {
+        Self {
+            payload_size: 2,
+            no_data: -1,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Packet {
+    pub from: DataType,
+    pub to: DataType,
+    pub payload: Vec<DataType>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PacketLogEntry {
+    pub round: usize,
+    pub packet: Packet,
+}
+
+/// Maps destination addresses to nodes. Node `i` has address `i` by default.
+#[derive(Debug, Default, Clone)]
+pub struct RoutingTable {
+    routes: HashMap<DataType, usize>,
+}
+
+impl RoutingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn identity(count: usize) -> Self {
+        let mut table = Self::new();
+        for node in 0..count {
+            table.add(node as DataType, node);
+        }
+        table
+    }
+
+    pub fn add(&mut self, addr: DataType, node: usize) {
+        self.routes.insert(addr, node);
+    }
+
+    pub fn remove(&mut self, addr: DataType) {
+        self.routes.remove(&addr);
+    }
+
+    pub fn node(&self, addr: DataType) -> Option<usize> {
+        self.routes.get(&addr).cloned()
+    }
+}
+
+/// Network is idle after `rounds` consecutive rounds without packets delivered to nodes
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdlePolicy {
+    pub rounds: usize,
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        Self { rounds: 1 }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HookAction {
+    None,
+    Send(Packet),
+    /// Stop the network with result
+    Stop(DataType),
+}
+
+/// Handles packets sent to the address it is attached to
+pub trait NetworkHook {
+    fn packet(&mut self, _packet: &Packet) -> Result<HookAction> {
+        Ok(HookAction::None)
+    }
+
+    fn idle(&mut self) -> Result<HookAction> {
+        Ok(HookAction::None)
+    }
+}
+
+/// Stops the network with the last payload value of the first received packet
+#[derive(Default)]
+pub struct FirstPacketMonitor;
+
+impl NetworkHook for FirstPacketMonitor {
+    fn packet(&mut self, packet: &Packet) -> Result<HookAction> {
+        match packet.payload.last() {
+            Some(value) => Ok(HookAction::Stop(*value)),
+            None => bail!("Empty packet payload from {}", packet.from),
+        }
+    }
+}
+
+/// Keeps the last received packet and resends it to `target` when the network is idle.
+/// Stops the network when the same last payload value is resent twice in a row.
+pub struct Nat {
+    addr: DataType,
+    target: DataType,
+    last: Option<Vec<DataType>>,
+    last_sent: Option<DataType>,
+}
+
+impl Nat {
+    pub fn new(addr: DataType, target: DataType) -> Self {
+        Self {
+            addr,
+            target,
+            last: None,
+            last_sent: None,
+        }
+    }
+}
+
+impl NetworkHook for Nat {
+    fn packet(&mut self, packet: &Packet) -> Result<HookAction> {
+        self.last = Some(packet.payload.clone());
+        Ok(HookAction::None)
+    }
+
+    fn idle(&mut self) -> Result<HookAction> {
+        let payload = match &self.last {
+            Some(payload) => payload.clone(),
+            None => return Ok(HookAction::None),
+        };
+        let value = payload.last().cloned();
+
+        if let Some(value) = value.filter(|v| Some(*v) == self.last_sent) {
+            return Ok(HookAction::Stop(value));
+        }
+
+        self.last_sent = value;
+
+        Ok(HookAction::Send(Packet {
+            from: self.addr,
+            to: self.target,
+            payload,
+        }))
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct NodeStats {
+    pub sent: usize,
+    pub received: usize,
+    /// Rounds when node got `PacketSchema::no_data`
+    pub empty_reads: usize,
+}
+
+/// Nodes run the same program one after another in rounds. Packets sent in a round
+/// are delivered to the node inputs after the round.
+pub struct Network<'l> {
+    nodes: Vec<IntcodeComp<'l>>,
+    schema: PacketSchema,
+    routes: RoutingTable,
+    idle_policy: IdlePolicy,
+    hooks: BTreeMap<DataType, Box<dyn NetworkHook>>,
+    queues: Vec<Vec<DataType>>,
+    stats: Vec<NodeStats>,
+    packet_log: Vec<PacketLogEntry>,
+    round: usize,
+    log: &'l Log,
+}
+
+impl<'l> Network<'l> {
+    /// Every node gets its address as the first input
+    pub fn new(prog: &str, count: usize, log: &'l Log) -> Result<Self> {
+        let mut nodes = Vec::new();
+
+        for i in 0..count {
+            let mut comp = IntcodeComp::new(Vec::new(), log);
+            comp.load_prog(prog)?;
+            comp.add_input(i as DataType);
+            nodes.push(comp);
+        }
+
+        Ok(Self {
+            nodes,
+            schema: PacketSchema::default(),
+            routes: RoutingTable::identity(count),
+            idle_policy: IdlePolicy::default(),
+            hooks: BTreeMap::new(),
+            queues: vec![Vec::new(); count],
+            stats: vec![NodeStats::default(); count],
+            packet_log: Vec::new(),
+            round: 0,
+            log,
+        })
+    }
+
+    pub fn set_schema(&mut self, schema: PacketSchema) {
+        self.schema = schema;
+    }
+
+    pub fn set_routing_table(&mut self, routes: RoutingTable) {
+        self.routes = routes;
+    }
+
+    pub fn set_idle_policy(&mut self, idle_policy: IdlePolicy) {
+        self.idle_policy = idle_policy;
+    }
+
+    pub fn add_hook(&mut self, addr: DataType, hook: Box<dyn NetworkHook>) {
+        self.hooks.insert(addr, hook);
+    }
+
+    pub fn nodes(&self) -> &[IntcodeComp<'l>] {
+        &self.nodes
+    }
+
+    pub fn stats(&self) -> &[NodeStats] {
+        &self.stats
+    }
+
+    pub fn packet_log(&self) -> &[PacketLogEntry] {
+        &self.packet_log
+    }
+
+    /// Number of finished rounds
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    /// Run until some hook stops the network
+    pub fn run(&mut self) -> Result<DataType> {
+        self.feed_inputs();
+
+        let mut idle_rounds = 0;
+
+        loop {
+            ensure!(
+                self.nodes.iter().any(|n| !n.is_halted()),
+                "All nodes are halted. Round: {}",
+                self.round
+            );
+
+            for node in 0..self.nodes.len() {
+                if self.nodes[node].is_halted() {
+                    continue;
+                }
+
+                self.nodes[node].run()?;
+
+                let output = self.nodes[node].get_output();
+                let packet_len = self.schema.payload_size + 1;
+
+                ensure!(
+                    output.len().is_multiple_of(packet_len),
+                    "Node {} output {} values which is not a whole number of packets",
+                    node,
+                    output.len()
+                );
+
+                for chunk in output.chunks(packet_len) {
+                    self.stats[node].sent += 1;
+
+                    let packet = Packet {
+                        from: node as DataType,
+                        to: chunk[0],
+                        payload: chunk[1..].to_vec(),
+                    };
+
+                    if let Some(result) = self.deliver(packet)? {
+                        return Ok(result);
+                    }
+                }
+            }
+
+            idle_rounds = if self.queues.iter().all(|q| q.is_empty()) {
+                idle_rounds + 1
+            } else {
+                0
+            };
+
+            if idle_rounds >= self.idle_policy.rounds {
+                self.log
+                    .println(format!("=> Network is idle. Round: {}", self.round));
+
+                idle_rounds = 0;
+
+                let addrs: Vec<DataType> = self.hooks.keys().cloned().collect();
+                for addr in addrs {
+                    let action = match self.hooks.get_mut(&addr) {
+                        Some(hook) => hook.idle()?,
+                        None => continue,
+                    };
+                    if let Some(result) = self.apply(action)? {
+                        return Ok(result);
+                    }
+                }
+            }
+
+            self.feed_inputs();
+            self.round += 1;
+        }
+    }
+
+    /// Returns result if network should be stopped
+    fn deliver(&mut self, packet: Packet) -> Result<Option<DataType>> {
+        self.packet_log.push(PacketLogEntry {
+            round: self.round,
+            packet: packet.clone(),
+        });
+
+        if let Some(node) = self.routes.node(packet.to) {
+            ensure!(
+                node < self.nodes.len(),
+                "Address {} is routed to unknown node {}",
+                packet.to,
+                node
+            );
+
+            self.queues[node].extend(packet.payload);
+            self.stats[node].received += 1;
+
+            return Ok(None);
+        }
+
+        let action = match self.hooks.get_mut(&packet.to) {
+            Some(hook) => hook.packet(&packet)?,
+            None => bail!(
+                "No route to address {}. Packet from {}.",
+                packet.to,
+                packet.from
+            ),
+        };
+
+        self.apply(action)
+    }
+
+    fn apply(&mut self, action: HookAction) -> Result<Option<DataType>> {
+        match action {
+            HookAction::None => Ok(None),
+            HookAction::Send(packet) => self.deliver(packet),
+            HookAction::Stop(result) => Ok(Some(result)),
+        }
+    }
+
+    fn feed_inputs(&mut self) {
+        for (node, queue) in self.queues.iter_mut().enumerate() {
+            if queue.is_empty() {
+                self.nodes[node].add_input(self.schema.no_data);
+                self.stats[node].empty_reads += 1;
+            } else {
+                self.nodes[node].add_input_vec(queue);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+
+    // Node 0 sends (7, 1) to node 1 at boot. Every received packet is forwarded
+    // to the address 255 with y incremented while it is less than 3.
+    const NODE: &str = "
+            GET  [addr]
+            EQ   [addr], #0, [t]
+            JIF  [t], #loop
+            SET  #1
+            SET  #7
+            SET  #1
+    loop:   GET  [x]
+            EQ   [x], #-1, [t]
+            JIT  [t], #loop
+            GET  [y]
+            LT   [y], #3, [t]
+            ADD  [y], [t], [y]
+            SET  #255
+            SET  [x]
+            SET  [y]
+            JIT  #1, #loop
+    addr:   db 0
+    x:      db 0
+    y:      db 0
+    t:      db 0
+    ";
+
+    fn network(log: &Log) -> Result<Network<'_>> {
+        let prog = to_prog_str(&assemble(NODE)?);
+        Network::new(&prog, 2, log)
+    }
+
+    #[test]
+    fn test_first_packet() -> Result<()> {
+        let log = Log::new(false);
+        let mut network = network(&log)?;
+
+        network.add_hook(255, Box::new(FirstPacketMonitor));
+        assert_eq!(network.run()?, 2);
+
+        let log = network.packet_log();
+        assert_eq!(log.len(), 2);
+        assert_eq!(
+            log[1],
+            PacketLogEntry {
+                round: 1,
+                packet: Packet {
+                    from: 1,
+                    to: 255,
+                    payload: vec![7, 2]
+                }
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_nat() -> Result<()> {
+        let log = Log::new(false);
+        let mut network = network(&log)?;
+
+        network.add_hook(255, Box::new(Nat::new(255, 0)));
+        assert_eq!(network.run()?, 3);
+
+        let resent: Vec<DataType> = network
+            .packet_log()
+            .iter()
+            .filter(|e| e.packet.from == 255)
+            .map(|e| e.packet.payload[1])
+            .collect();
+        assert_eq!(resent, vec![2, 3]);
+
+        assert_eq!(network.stats()[0].sent, 3);
+        assert_eq!(network.stats()[0].received, 2);
+        assert_eq!(network.stats()[1].received, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_routing() -> Result<()> {
+        let log = Log::new(false);
+        let mut network = network(&log)?;
+
+        let mut routes = RoutingTable::identity(2);
+        routes.remove(1);
+        network.set_routing_table(routes);
+
+        let err = network.run().unwrap_err().to_string();
+        assert_eq!(err, "No route to address 1. Packet from 0.");
+        Ok(())
+    }
+}
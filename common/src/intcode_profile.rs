@@ -0,0 +1,336 @@
+use crate::intcode_comp::*;
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IpStats {
+    pub mnemonic: &'static str,
+    pub count: u64,
+}
+
+/// Straight run of instructions ending with a jump or halt.
+/// Blocks are identified by the ip they were entered at.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct BlockStats {
+    /// Number of times the block was entered
+    pub entries: u64,
+    pub instructions: u64,
+    /// Highest ip executed inside the block
+    pub last_ip: usize,
+}
+
+/// Execution counts collected by `IntcodeComp` in profiling mode.
+///
+/// Calls are recognized by the usual stack convention: taken jump to a fixed target
+/// right after the address of the next instruction was written using relative mode.
+/// Jump to the address read using relative mode returns from the call.
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    total: u64,
+    ips: HashMap<usize, IpStats>,
+    opcodes: BTreeMap<&'static str, u64>,
+    blocks: HashMap<usize, BlockStats>,
+    reads: HashMap<usize, u64>,
+    writes: HashMap<usize, u64>,
+    /// Current block entry ip
+    block: Option<usize>,
+    /// Last value written using relative mode
+    pushed: Option<DataType>,
+    /// Function entry and return address for every open call
+    frames: Vec<(usize, usize)>,
+    stack_ids: HashMap<Vec<usize>, usize>,
+    stacks: Vec<Vec<usize>>,
+    stack: usize,
+    /// Instructions executed per stack and block
+    folded: HashMap<(usize, usize), u64>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        let mut profile = Self::default();
+        profile.stacks.push(Vec::new());
+        profile.stack_ids.insert(Vec::new(), 0);
+        profile
+    }
+
+    /// Number of executed instructions
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn ips(&self) -> &HashMap<usize, IpStats> {
+        &self.ips
+    }
+
+    pub fn opcodes(&self) -> &BTreeMap<&'static str, u64> {
+        &self.opcodes
+    }
+
+    pub fn blocks(&self) -> &HashMap<usize, BlockStats> {
+        &self.blocks
+    }
+
+    /// Number of reads per address made by instruction parameters
+    pub fn reads(&self) -> &HashMap<usize, u64> {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &HashMap<usize, u64> {
+        &self.writes
+    }
+
+    pub(crate) fn read(&mut self, addr: usize) {
+        *self.reads.entry(addr).or_default() += 1;
+    }
+
+    pub(crate) fn write(&mut self, addr: usize, value: DataType, mode: ParamMode) {
+        *self.writes.entry(addr).or_default() += 1;
+
+        if mode == ParamMode::Relative {
+            self.pushed = Some(value);
+        }
+    }
+
+    /// Account executed instruction. `next_ip` is the address of the following
+    /// instruction in memory, `new_ip` is where execution continues.
+    pub(crate) fn instruction(&mut self, ip: usize, cmd: Command, next_ip: usize, new_ip: usize) {
+        let mnemonic = cmd.mnemonic();
+        self.total += 1;
+
+        self.ips
+            .entry(ip)
+            .or_insert(IpStats { mnemonic, count: 0 })
+            .count += 1;
+        *self.opcodes.entry(mnemonic).or_default() += 1;
+
+        let entered = self.block.is_none();
+        let block_ip = *self.block.get_or_insert(ip);
+        let block = self.blocks.entry(block_ip).or_default();
+        if entered {
+            block.entries += 1;
+        }
+        block.instructions += 1;
+        block.last_ip = block.last_ip.max(ip);
+
+        *self.folded.entry((self.stack, block_ip)).or_default() += 1;
+
+        let target_mode = match cmd {
+            Command::JumpIfTrue(_, mode) | Command::JumpIfFalse(_, mode) => mode,
+            Command::Exit => {
+                self.end_block();
+                return;
+            }
+            Command::Ext(_) if new_ip != next_ip => {
+                self.end_block();
+                return;
+            }
+            _ => return,
+        };
+
+        self.end_block();
+
+        if new_ip == next_ip {
+            return;
+        }
+
+        if target_mode == ParamMode::Relative {
+            if let Some(pos) = self.frames.iter().rposition(|&(_, ret)| ret == new_ip) {
+                self.frames.truncate(pos);
+                self.update_stack();
+            }
+        } else if self.pushed == Some(next_ip as DataType) {
+            self.frames.push((new_ip, next_ip));
+            self.update_stack();
+        }
+
+        self.pushed = None;
+    }
+
+    /// Next instruction starts a new block
+    pub(crate) fn end_block(&mut self) {
+        self.block = None;
+    }
+
+    fn update_stack(&mut self) {
+        let stack: Vec<usize> = self.frames.iter().map(|&(entry, _)| entry).collect();
+        let next_id = self.stacks.len();
+
+        self.stack = *self.stack_ids.entry(stack.clone()).or_insert(next_id);
+        if self.stack == next_id {
+            self.stacks.push(stack);
+        }
+    }
+
+    /// Text report with `top` hottest entries in every section
+    pub fn report(&self, top: usize) -> String {
+        let mut out = String::new();
+        let percent = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+
+        let _ = writeln!(out, "Instructions: {}", self.total);
+
+        let _ = writeln!(out, "\nOpcodes:");
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (mnemonic, count) in opcodes {
+            let _ = writeln!(
+                out,
+                "  {:>4} {:>12} {:6.2}%",
+                mnemonic,
+                count,
+                percent(*count)
+            );
+        }
+
+        let _ = writeln!(out, "\nHot instructions:");
+        for (ip, stats) in hottest(&self.ips, top, |s| s.count) {
+            let _ = writeln!(
+                out,
+                "  [{:5}] {:>4} {:>12} {:6.2}%",
+                ip,
+                stats.mnemonic,
+                stats.count,
+                percent(stats.count)
+            );
+        }
+
+        let _ = writeln!(out, "\nHot blocks:");
+        for (ip, block) in hottest(&self.blocks, top, |b| b.instructions) {
+            let _ = writeln!(
+                out,
+                "  [{:5}..{:5}] entries {:>10} instructions {:>12} {:6.2}%",
+                ip,
+                block.last_ip,
+                block.entries,
+                block.instructions,
+                percent(block.instructions)
+            );
+        }
+
+        for (title, heatmap) in &[("reads", &self.reads), ("writes", &self.writes)] {
+            let _ = writeln!(out, "\nHot memory {}:", title);
+            for (addr, count) in hottest(heatmap, top, |c| *c) {
+                let _ = writeln!(out, "  [{:5}] {:>12}", addr, count);
+            }
+        }
+
+        out
+    }
+
+    /// Folded stacks for flamegraph tools: `main;func_<entry>;block_<ip> <count>` per line
+    pub fn write_folded<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut lines: Vec<(String, u64)> = self
+            .folded
+            .iter()
+            .map(|(&(stack, block), &count)| {
+                let mut line = "main".to_string();
+                for ip in &self.stacks[stack] {
+                    line += &format!(";func_{}", ip);
+                }
+                line += &format!(";block_{}", block);
+                (line, count)
+            })
+            .collect();
+        lines.sort();
+
+        for (line, count) in lines {
+            writeln!(writer, "{} {}", line, count)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_folded<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_folded(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Entries with the highest counts, ties are ordered by key
+fn hottest<V, F>(map: &HashMap<usize, V>, top: usize, count: F) -> Vec<(usize, &V)>
+where
+    F: Fn(&V) -> u64,
+{
+    let mut entries: Vec<(usize, &V)> = map.iter().map(|(k, v)| (*k, v)).collect();
+    entries.sort_by(|a, b| count(b.1).cmp(&count(a.1)).then(a.0.cmp(&b.0)));
+    entries.truncate(top);
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_asm::*;
+    use crate::log::*;
+
+    // Prints doubled 3, 2 and 1
+    const PROG: &str = "
+              ARB  #stack
+        loop: CALL double
+              ADD  [n], #-1, [n]
+              JIT  [n], #loop
+              EXIT
+      double: MUL  [n], #2, [t]
+              SET  [t]
+              RET
+        n:    db 3
+        t:    db 0
+        stack:
+    ";
+
+    #[test]
+    fn test_profile() -> Result<()> {
+        let log = Log::new(false);
+        let prog = assemble(PROG)?;
+        let mut comp = IntcodeComp::new(prog.clone(), &log);
+
+        comp.start_profiling();
+        comp.exec()?;
+        assert_eq!(comp.get_output(), vec![6, 4, 2]);
+
+        let profile = comp.stop_profiling().unwrap();
+        assert_eq!(profile.total(), comp.get_steps());
+        assert_eq!(profile.opcodes()["ARB"], 7);
+        assert_eq!(profile.opcodes()["EXIT"], 1);
+
+        // ARB(2) + CALL(9) + ADD(4) + JIT(3) + EXIT(1)
+        let double = 19;
+        assert_eq!(profile.ips()[&double].count, 3);
+        assert_eq!(
+            profile.blocks()[&double],
+            BlockStats {
+                entries: 3,
+                instructions: 12,
+                last_ip: double + 8
+            }
+        );
+
+        let n = double + 11;
+        assert_eq!(profile.reads()[&n], 9);
+        assert_eq!(profile.writes()[&n], 3);
+
+        let mut folded = Vec::new();
+        profile.write_folded(&mut folded)?;
+        assert_eq!(
+            String::from_utf8(folded)?,
+            format!(
+                "main;block_0 4\nmain;block_11 6\nmain;block_18 1\nmain;block_2 6\nmain;func_{0};block_{0} 12\n",
+                double
+            )
+        );
+
+        let report = profile.report(3);
+        assert!(report.starts_with("Instructions: 29\n"));
+        assert!(report.contains(&format!(
+            "[{:5}..{:5}] entries          3",
+            double,
+            double + 8
+        )));
+        Ok(())
+    }
+}
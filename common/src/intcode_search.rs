@@ -0,0 +1,177 @@
+use crate::intcode_comp::DataType;
+use anyhow::Result;
+use rayon::prelude::*;
+use std::ops::Range;
+
+/// Set of parameter vectors to try
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParamSpace {
+    /// All orderings of the values
+    Permutations(Vec<DataType>),
+    /// Single parameter
+    Range(Range<DataType>),
+    /// Every combination of the sub-spaces candidates joined together
+    Product(Vec<ParamSpace>),
+}
+
+impl ParamSpace {
+    /// Candidates in lexicographic order of the source values
+    pub fn candidates(&self) -> Vec<Vec<DataType>> {
+        match self {
+            ParamSpace::Permutations(values) => {
+                let mut result = Vec::new();
+                permute(&mut values.clone(), 0, &mut result);
+                result
+            }
+            ParamSpace::Range(range) => range.clone().map(|v| vec![v]).collect(),
+            ParamSpace::Product(spaces) => spaces.iter().fold(vec![Vec::new()], |acc, space| {
+                let candidates = space.candidates();
+                acc.iter()
+                    .flat_map(|prefix| {
+                        candidates.iter().map(move |c| {
+                            let mut v = prefix.clone();
+                            v.extend(c);
+                            v
+                        })
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+fn permute(values: &mut Vec<DataType>, start: usize, result: &mut Vec<Vec<DataType>>) {
+    if start >= values.len() {
+        result.push(values.clone());
+        return;
+    }
+
+    for i in start..values.len() {
+        // Rotate instead of swap to keep lexicographic order
+        values[start..=i].rotate_right(1);
+        permute(values, start + 1, result);
+        values[start..=i].rotate_left(1);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SearchResult<S> {
+    pub score: S,
+    /// All candidates with the best score in the candidates order
+    pub best: Vec<Vec<DataType>>,
+}
+
+/// Evaluate every candidate in parallel and return the ones with the highest score.
+/// Evaluation returns None for rejected candidates. First evaluation error is returned.
+pub fn search<P, S, F>(prog: &P, space: &ParamSpace, eval: F) -> Result<Option<SearchResult<S>>>
+where
+    P: Sync + ?Sized,
+    S: Ord + Send,
+    F: Fn(&P, &[DataType]) -> Result<Option<S>> + Sync,
+{
+    let candidates = space.candidates();
+    let scores = candidates
+        .par_iter()
+        .map(|c| eval(prog, c))
+        .collect::<Result<Vec<Option<S>>>>()?;
+
+    let mut result: Option<SearchResult<S>> = None;
+
+    for (candidate, score) in candidates.into_iter().zip(scores) {
+        let score = match score {
+            Some(score) => score,
+            None => continue,
+        };
+
+        match &mut result {
+            Some(best) if best.score == score => best.best.push(candidate),
+            Some(best) if best.score > score => {}
+            _ => {
+                result = Some(SearchResult {
+                    score,
+                    best: vec![candidate],
+                })
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_comp::*;
+    use crate::log::*;
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            ParamSpace::Permutations(vec![1, 2, 3]).candidates(),
+            vec![
+                vec![1, 2, 3],
+                vec![1, 3, 2],
+                vec![2, 1, 3],
+                vec![2, 3, 1],
+                vec![3, 1, 2],
+                vec![3, 2, 1]
+            ]
+        );
+        assert_eq!(
+            ParamSpace::Product(vec![
+                ParamSpace::Range(0..2),
+                ParamSpace::Permutations(vec![5, 6])
+            ])
+            .candidates(),
+            vec![vec![0, 5, 6], vec![0, 6, 5], vec![1, 5, 6], vec![1, 6, 5]]
+        );
+    }
+
+    #[test]
+    fn test_phases() -> Result<()> {
+        // Day 7 example. Max thruster signal 43210 for phases 4,3,2,1,0.
+        let prog = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        let space = ParamSpace::Permutations((0..5).collect());
+
+        let result = search(&prog, &space, |prog, phases| {
+            let log = Log::new(false);
+            let mut signal = 0;
+            for phase in phases {
+                let mut comp = IntcodeComp::new(prog.clone(), &log);
+                comp.add_input_vec(&mut vec![*phase, signal]);
+                comp.exec()?;
+                signal = comp.get_output()[0];
+            }
+            Ok(Some(signal))
+        })?;
+
+        assert_eq!(
+            result,
+            Some(SearchResult {
+                score: 43210,
+                best: vec![vec![4, 3, 2, 1, 0]]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ties() -> Result<()> {
+        let space = ParamSpace::Product(vec![ParamSpace::Range(0..10), ParamSpace::Range(0..10)]);
+
+        // Pairs with the sum 9 closest to the target
+        let result = search(&9, &space, |target, v| {
+            Ok(Some(-(v[0] + v[1] - target).abs()))
+        })?
+        .unwrap();
+        assert_eq!(result.score, 0);
+        assert_eq!(result.best.len(), 10);
+        assert_eq!(result.best[0], vec![0, 9]);
+
+        let result = search(&(), &space, |_, _| Ok(None::<DataType>))?;
+        assert_eq!(result, None);
+        Ok(())
+    }
+}
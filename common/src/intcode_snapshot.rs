@@ -0,0 +1,434 @@
+use crate::intcode_comp::*;
+use crate::intcode_limits::*;
+use crate::intcode_memory::*;
+use anyhow::{anyhow, bail, ensure, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// Every change of the layout gets a new version. Older versions are still loaded
+/// and converted to the current one.
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+/// Dense memory copy, no halt address, no interrupted statuses
+const SNAPSHOT_VERSION_1: u32 = 1;
+/// Interrupted statuses added
+const SNAPSHOT_VERSION_2: u32 = 2;
+/// Sparse memory image, no halt address
+const SNAPSHOT_VERSION_3: u32 = 3;
+
+const MAGIC: &[u8; 4] = b"ICSN";
+
+/// Complete state of the `IntcodeComp` (except log and tracer)
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub memory: MemoryImage,
+    pub prog_backup: Vec<DataType>,
+    pub ip: usize,
+    /// Address of the instruction that halted the program
+    #[serde(default)]
+    pub halt_ip: Option<usize>,
+    pub rel_base: usize,
+    pub input: Vec<DataType>,
+    pub output: Vec<DataType>,
+    pub status: Status,
+    pub steps: u64,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct SnapshotV1 {
+    prog: Vec<DataType>,
+    prog_backup: Vec<DataType>,
+    ip: usize,
+    rel_base: usize,
+    input: Vec<DataType>,
+    output: Vec<DataType>,
+    status: Status,
+    steps: u64,
+}
+
+impl From<SnapshotV1> for Snapshot {
+    fn from(snapshot: SnapshotV1) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            memory: MemoryImage::from_vec(snapshot.prog),
+            prog_backup: snapshot.prog_backup,
+            ip: snapshot.ip,
+            halt_ip: None,
+            rel_base: snapshot.rel_base,
+            input: snapshot.input,
+            output: snapshot.output,
+            status: snapshot.status,
+            steps: snapshot.steps,
+        }
+    }
+}
+
+impl Snapshot {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(data: &str) -> Result<Self> {
+        let version: Version = serde_json::from_str(data)?;
+
+        match version.version {
+            SNAPSHOT_VERSION => Ok(serde_json::from_str(data)?),
+            SNAPSHOT_VERSION_3 => Ok(Self {
+                version: SNAPSHOT_VERSION,
+                ..serde_json::from_str(data)?
+            }),
+            SNAPSHOT_VERSION_1 | SNAPSHOT_VERSION_2 => {
+                let snapshot: SnapshotV1 = serde_json::from_str(data)?;
+                if version.version == SNAPSHOT_VERSION_1 {
+                    ensure!(
+                        !matches!(snapshot.status, Status::Interrupted(_)),
+                        "Unknown status {:?} in snapshot version 1.",
+                        snapshot.status
+                    );
+                }
+                Ok(snapshot.into())
+            }
+            version => Err(unsupported(version)),
+        }
+    }
+
+    /// Binary format: magic, version and then all fields in little endian
+    pub fn write_binary<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&(self.ip as u64).to_le_bytes())?;
+        writer.write_all(&self.halt_ip.map_or(u64::MAX, |ip| ip as u64).to_le_bytes())?;
+        writer.write_all(&(self.rel_base as u64).to_le_bytes())?;
+        writer.write_all(&self.steps.to_le_bytes())?;
+        writer.write_all(&[match self.status {
+            Status::Running => 0,
+            Status::WaitForInput => 1,
+            Status::Halted => 2,
+            Status::Interrupted(Interrupt::InstructionLimit) => 3,
+            Status::Interrupted(Interrupt::Timeout) => 4,
+            Status::Interrupted(Interrupt::Cancelled) => 5,
+        }])?;
+
+        writer.write_all(&(self.memory.size() as u64).to_le_bytes())?;
+        writer.write_all(&(self.memory.segments().len() as u64).to_le_bytes())?;
+        for (start, cells) in self.memory.segments() {
+            writer.write_all(&(*start as u64).to_le_bytes())?;
+            write_vec(writer, cells)?;
+        }
+
+        for data in &[&self.prog_backup, &self.input, &self.output] {
+            write_vec(writer, data)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_binary<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        ensure!(&magic == MAGIC, "Not an Intcode snapshot.");
+
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)?;
+        let version = u32::from_le_bytes(buf);
+        if !(SNAPSHOT_VERSION_1..=SNAPSHOT_VERSION).contains(&version) {
+            return Err(unsupported(version));
+        }
+
+        let ip = read_u64(reader)? as usize;
+        let halt_ip = match version {
+            SNAPSHOT_VERSION => match read_u64(reader)? {
+                u64::MAX => None,
+                halt_ip => Some(halt_ip as usize),
+            },
+            _ => None,
+        };
+        let rel_base = read_u64(reader)? as usize;
+        let steps = read_u64(reader)?;
+
+        let mut status = [0u8; 1];
+        reader.read_exact(&mut status)?;
+        let status = match (status[0], version) {
+            (0, _) => Status::Running,
+            (1, _) => Status::WaitForInput,
+            (2, _) => Status::Halted,
+            (3, SNAPSHOT_VERSION_2..=SNAPSHOT_VERSION) => {
+                Status::Interrupted(Interrupt::InstructionLimit)
+            }
+            (4, SNAPSHOT_VERSION_2..=SNAPSHOT_VERSION) => Status::Interrupted(Interrupt::Timeout),
+            (5, SNAPSHOT_VERSION_2..=SNAPSHOT_VERSION) => Status::Interrupted(Interrupt::Cancelled),
+            (value, _) => bail!("Unknown status {} in snapshot version {}.", value, version),
+        };
+
+        let memory = match version {
+            SNAPSHOT_VERSION | SNAPSHOT_VERSION_3 => {
+                let size = read_u64(reader)? as usize;
+                let mut segments = Vec::new();
+                for _ in 0..read_u64(reader)? {
+                    let start = read_u64(reader)? as usize;
+                    segments.push((start, read_vec(reader)?));
+                }
+                MemoryImage::from_segments(size, segments)?
+            }
+            _ => MemoryImage::from_vec(read_vec(reader)?),
+        };
+
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            memory,
+            prog_backup: read_vec(reader)?,
+            input: read_vec(reader)?,
+            output: read_vec(reader)?,
+            ip,
+            halt_ip,
+            rel_base,
+            status,
+            steps,
+        })
+    }
+
+    /// Save to file. Files with `.json` extension use JSON format, others binary one.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(&path)?);
+
+        if is_json(path.as_ref()) {
+            serde_json::to_writer(&mut writer, self)?;
+        } else {
+            self.write_binary(&mut writer)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(&path)?);
+
+        if is_json(path.as_ref()) {
+            let mut data = String::new();
+            reader.read_to_string(&mut data)?;
+            Self::from_json(&data)
+        } else {
+            Self::read_binary(&mut reader)
+        }
+    }
+}
+
+fn unsupported(version: u32) -> anyhow::Error {
+    anyhow!(
+        "Unsupported snapshot version {}. Expected {}.",
+        version,
+        SNAPSHOT_VERSION
+    )
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().map(|e| e == "json").unwrap_or(false)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn write_vec<W: Write>(writer: &mut W, data: &[DataType]) -> Result<()> {
+    writer.write_all(&(data.len() as u64).to_le_bytes())?;
+    for value in data {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_vec<R: Read>(reader: &mut R) -> Result<Vec<DataType>> {
+    let len = read_u64(reader)? as usize;
+    let mut result = Vec::with_capacity(len.min(1 << 20));
+    let mut buf = [0u8; 8];
+
+    for _ in 0..len {
+        reader.read_exact(&mut buf)?;
+        result.push(DataType::from_le_bytes(buf));
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+
+    // Outputs sum of every two input values
+    const PROG: [DataType; 13] = [3, 13, 3, 14, 1, 13, 14, 13, 4, 13, 1105, 1, 0];
+
+    fn started_comp(log: &Log) -> Result<IntcodeComp<'_>> {
+        let mut comp = IntcodeComp::new(PROG.to_vec(), log);
+        comp.add_input_vec(&mut vec![1, 2, 3]);
+        comp.run()?;
+        Ok(comp)
+    }
+
+    #[test]
+    fn test_binary() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = started_comp(&log)?;
+        let mut data = Vec::new();
+
+        comp.snapshot().write_binary(&mut data)?;
+
+        let snapshot = Snapshot::read_binary(&mut data.as_slice())?;
+        assert_eq!(snapshot, comp.snapshot());
+
+        let mut restored = IntcodeComp::from_snapshot(snapshot, &log)?;
+        restored.add_input(10);
+        restored.run()?;
+        comp.add_input(10);
+        comp.run()?;
+
+        assert_eq!(restored.get_output(), vec![3, 13]);
+        assert_eq!(comp.get_output(), vec![3, 13]);
+        assert_eq!(restored.get_steps(), comp.get_steps());
+
+        let mut halted = IntcodeComp::new(vec![99], &log);
+        halted.exec()?;
+        let mut halted_data = Vec::new();
+        halted.snapshot().write_binary(&mut halted_data)?;
+        let snapshot = Snapshot::read_binary(&mut halted_data.as_slice())?;
+        assert_eq!(snapshot.halt_ip, Some(0));
+
+        data[4] = 99;
+        assert!(Snapshot::read_binary(&mut data.as_slice()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> Result<()> {
+        let log = Log::new(false);
+        let comp = started_comp(&log)?;
+        let json = comp.snapshot().to_json()?;
+
+        assert_eq!(Snapshot::from_json(&json)?, comp.snapshot());
+        assert!(Snapshot::from_json(&json.replace("\"version\":4", "\"version\":5")).is_err());
+
+        let mut other = IntcodeComp::new(Vec::new(), &log);
+        other.restore(Snapshot::from_json(&json)?)?;
+        assert_eq!(other.get_ip(), comp.get_ip());
+        assert_eq!(other.input(), comp.input());
+        assert_eq!(other.output(), &[3]);
+        Ok(())
+    }
+
+    /// Versions 1 and 2 layout: dense memory and no halt address
+    fn dense_binary(version: u32, comp: &IntcodeComp, status: u8) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&version.to_le_bytes());
+        for value in &[
+            comp.get_ip(),
+            comp.get_rel_base(),
+            comp.get_steps() as usize,
+        ] {
+            data.extend_from_slice(&(*value as u64).to_le_bytes());
+        }
+        data.push(status);
+        for values in &[
+            &comp.memory().to_vec(),
+            &PROG.to_vec(),
+            &comp.input().to_vec(),
+            &comp.output().to_vec(),
+        ] {
+            write_vec(&mut data, values)?;
+        }
+        Ok(data)
+    }
+
+    fn dense_json(version: u32, comp: &IntcodeComp, status: &str) -> String {
+        format!(
+            "{{\"version\":{},\"prog\":{:?},\"prog_backup\":{:?},\"ip\":{},\"rel_base\":{},\
+             \"input\":{:?},\"output\":{:?},\"status\":{},\"steps\":{}}}",
+            version,
+            comp.memory().to_vec(),
+            PROG,
+            comp.get_ip(),
+            comp.get_rel_base(),
+            comp.input(),
+            comp.output(),
+            status,
+            comp.get_steps()
+        )
+    }
+
+    #[test]
+    fn test_version_1() -> Result<()> {
+        let log = Log::new(false);
+        let comp = started_comp(&log)?;
+        let expected = comp.snapshot();
+
+        let data = dense_binary(SNAPSHOT_VERSION_1, &comp, 1)?;
+        assert_eq!(Snapshot::read_binary(&mut data.as_slice())?, expected);
+        let json = dense_json(SNAPSHOT_VERSION_1, &comp, "\"WaitForInput\"");
+        assert_eq!(Snapshot::from_json(&json)?, expected);
+
+        // Interrupted statuses appeared in version 2
+        let data = dense_binary(SNAPSHOT_VERSION_1, &comp, 3)?;
+        assert!(Snapshot::read_binary(&mut data.as_slice()).is_err());
+        let json = dense_json(SNAPSHOT_VERSION_1, &comp, r#"{"Interrupted":"Timeout"}"#);
+        assert!(Snapshot::from_json(&json).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_2() -> Result<()> {
+        let log = Log::new(false);
+        let mut comp = IntcodeComp::new(PROG.to_vec(), &log);
+        comp.add_input_vec(&mut vec![1, 2, 3]);
+        comp.set_instruction_limit(Some(5));
+        comp.run()?;
+        let expected = comp.snapshot();
+        assert_eq!(
+            expected.status,
+            Status::Interrupted(Interrupt::InstructionLimit)
+        );
+
+        let data = dense_binary(SNAPSHOT_VERSION_2, &comp, 3)?;
+        assert_eq!(Snapshot::read_binary(&mut data.as_slice())?, expected);
+        let json = dense_json(
+            SNAPSHOT_VERSION_2,
+            &comp,
+            r#"{"Interrupted":"InstructionLimit"}"#,
+        );
+        assert_eq!(Snapshot::from_json(&json)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_3() -> Result<()> {
+        let log = Log::new(false);
+        let comp = started_comp(&log)?;
+        let expected = comp.snapshot();
+
+        // Current layout without the halt address
+        let mut data = Vec::new();
+        expected.write_binary(&mut data)?;
+        data.splice(16..24, Vec::new());
+        data[4..8].copy_from_slice(&SNAPSHOT_VERSION_3.to_le_bytes());
+        assert_eq!(Snapshot::read_binary(&mut data.as_slice())?, expected);
+
+        let json = expected
+            .to_json()?
+            .replace("\"version\":4", "\"version\":3")
+            .replace("\"halt_ip\":null,", "");
+        assert!(!json.contains("halt_ip"));
+        assert_eq!(Snapshot::from_json(&json)?, expected);
+        Ok(())
+    }
+}
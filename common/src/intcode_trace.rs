@@ -0,0 +1,309 @@
+use crate::intcode_comp::*;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Parameter of the executed instruction
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct TraceParam {
+    pub mode: ParamMode,
+    /// Value stored in the instruction
+    pub raw: DataType,
+    /// Memory address that was accessed. None for immediate parameters.
+    pub addr: Option<usize>,
+    /// Value that was read or written
+    pub value: DataType,
+    pub write: bool,
+}
+
+/// Everything that happened while executing one instruction
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct TraceEvent {
+    /// Number of instructions executed before this one
+    pub step: u64,
+    pub ip: usize,
+    pub opcode: DataType,
+    pub mnemonic: &'static str,
+    pub params: Vec<TraceParam>,
+    pub rel_base: usize,
+    /// New relative base if instruction changed it
+    pub new_rel_base: Option<usize>,
+    pub input: Option<DataType>,
+    pub output: Option<DataType>,
+    /// Instruction was not executed because input queue is empty
+    pub wait_for_input: bool,
+}
+
+impl TraceEvent {
+    pub fn new(
+        step: u64,
+        ip: usize,
+        opcode: DataType,
+        mnemonic: &'static str,
+        rel_base: usize,
+    ) -> Self {
+        Self {
+            step,
+            ip,
+            opcode,
+            mnemonic,
+            params: Vec::new(),
+            rel_base,
+            new_rel_base: None,
+            input: None,
+            output: None,
+            wait_for_input: false,
+        }
+    }
+
+    pub fn reads(&self) -> impl Iterator<Item = (usize, DataType)> + '_ {
+        self.params
+            .iter()
+            .filter(|p| !p.write)
+            .filter_map(|p| p.addr.map(|addr| (addr, p.value)))
+    }
+
+    pub fn writes(&self) -> impl Iterator<Item = (usize, DataType)> + '_ {
+        self.params
+            .iter()
+            .filter(|p| p.write)
+            .filter_map(|p| p.addr.map(|addr| (addr, p.value)))
+    }
+}
+
+/// Same format as `IntcodeComp` used to print with active `Log`
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:4}] {:>3}", self.ip, self.mnemonic)?;
+
+        for param in &self.params {
+            let arrow = if param.write { "<-" } else { "->" };
+            match param.mode {
+                ParamMode::Position => write!(f, " p[{}]{}{}", param.raw, arrow, param.value)?,
+                ParamMode::Immediate => write!(f, " i[{}]", param.value)?,
+                ParamMode::Relative => write!(
+                    f,
+                    " r[{}+{}]{}{}",
+                    param.raw, self.rel_base, arrow, param.value
+                )?,
+                ParamMode::Custom(mode) => {
+                    write!(f, " m{}[{}]{}{}", mode, param.raw, arrow, param.value)?
+                }
+            }
+        }
+
+        if let Some(rel_base) = self.new_rel_base {
+            write!(f, "->{}", rel_base)?;
+        }
+
+        if self.wait_for_input {
+            write!(f, "    Waiting for input")?;
+        }
+
+        Ok(())
+    }
+}
+
+pub trait TraceSink {
+    fn event(&mut self, event: &TraceEvent) -> Result<()>;
+}
+
+pub type SharedTraceSink = Arc<Mutex<dyn TraceSink + Send>>;
+
+/// Keeps last `capacity` events in memory
+pub struct RingBufferSink {
+    events: VecDeque<TraceEvent>,
+    capacity: usize,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn events(&self) -> &VecDeque<TraceEvent> {
+        &self.events
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl TraceSink for RingBufferSink {
+    fn event(&mut self, event: &TraceEvent) -> Result<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+        Ok(())
+    }
+}
+
+/// Sends every event to both sinks
+pub struct TeeSink {
+    first: SharedTraceSink,
+    second: SharedTraceSink,
+}
+
+impl TeeSink {
+    pub fn new(first: SharedTraceSink, second: SharedTraceSink) -> Self {
+        Self { first, second }
+    }
+}
+
+impl TraceSink for TeeSink {
+    fn event(&mut self, event: &TraceEvent) -> Result<()> {
+        for sink in &[&self.first, &self.second] {
+            sink.lock()
+                .map_err(|_| anyhow!("Trace sink is poisoned"))?
+                .event(event)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes one JSON object per event
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl JsonLinesSink<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> TraceSink for JsonLinesSink<W> {
+    fn event(&mut self, event: &TraceEvent) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, event)?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+}
+
+/// Human readable format, one line per event
+pub struct TextSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TextSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> TraceSink for TextSink<W> {
+    fn event(&mut self, event: &TraceEvent) -> Result<()> {
+        writeln!(self.writer, "{}", event)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::*;
+
+    #[test]
+    fn test_events() -> Result<()> {
+        let log = Log::new(false);
+        let ring = Arc::new(Mutex::new(RingBufferSink::new(3)));
+        let mut comp = IntcodeComp::new(vec![109, 11, 203, 0, 1001, 11, 7, 12, 4, 12, 99], &log);
+
+        comp.set_tracer(ring.clone());
+        comp.add_input(10);
+        comp.exec()?;
+
+        let ring = ring.lock().unwrap();
+        let events: Vec<String> = ring.events().iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            events,
+            vec![
+                "[   4] ADD p[11]->10 i[7] p[12]<-17",
+                "[   8] SET p[12]->17",
+                "[  10] EXIT",
+            ]
+        );
+
+        let add = &ring.events()[0];
+        assert_eq!(add.step, 2);
+        assert_eq!(add.reads().collect::<Vec<_>>(), vec![(11, 10)]);
+        assert_eq!(add.writes().collect::<Vec<_>>(), vec![(12, 17)]);
+        assert_eq!(ring.events()[1].output, Some(17));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_lines() -> Result<()> {
+        let log = Log::new(false);
+        let json = Arc::new(Mutex::new(JsonLinesSink::new(Vec::new())));
+        let mut comp = IntcodeComp::new(vec![3, 0, 109, 3, 99], &log);
+
+        comp.set_tracer(json.clone());
+        comp.run()?;
+        comp.add_input(1);
+        comp.run()?;
+
+        let text = String::from_utf8(json.lock().unwrap().writer.clone())?;
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<_, _>>()?;
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["wait_for_input"], true);
+        assert_eq!(lines[1]["input"], 1);
+        assert_eq!(lines[1]["params"][0]["addr"], 0);
+        assert_eq!(lines[2]["mnemonic"], "ARB");
+        assert_eq!(lines[2]["new_rel_base"], 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee() -> Result<()> {
+        let log = Log::new(false);
+        let first = Arc::new(Mutex::new(RingBufferSink::new(3)));
+        let second = Arc::new(Mutex::new(RingBufferSink::new(3)));
+        let mut comp = IntcodeComp::new(vec![104, 1, 99], &log);
+
+        comp.set_tracer(Arc::new(Mutex::new(TeeSink::new(
+            first.clone(),
+            second.clone(),
+        ))));
+        comp.exec()?;
+        assert_eq!(first.lock().unwrap().events().len(), 2);
+        assert_eq!(second.lock().unwrap().events().len(), 2);
+
+        // Sink poisoned by a panic while it was locked
+        let poisoned = second.clone();
+        let _ = std::thread::spawn(move || {
+            let _sink = poisoned.lock().unwrap();
+            panic!("poison");
+        })
+        .join();
+
+        comp.reset();
+        let err = comp.exec().err().unwrap();
+        assert!(format!("{:#}", err).contains("Trace sink is poisoned"));
+        Ok(())
+    }
+}
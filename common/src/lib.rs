@@ -1,5 +1,29 @@
 pub mod color_text;
 pub mod dynamic_map;
+pub mod intcode_analysis;
+pub mod intcode_ascii;
+pub mod intcode_asm;
+pub mod intcode_bigint;
 pub mod intcode_comp;
+pub mod intcode_conformance;
+pub mod intcode_debug;
+pub mod intcode_decode;
+pub mod intcode_device;
+pub mod intcode_diff;
+pub mod intcode_disasm;
+pub mod intcode_error;
+pub mod intcode_executor;
+pub mod intcode_fuzz;
+pub mod intcode_graph;
+pub mod intcode_isa;
+pub mod intcode_journal;
+pub mod intcode_limits;
+pub mod intcode_loader;
+pub mod intcode_memory;
+pub mod intcode_network;
+pub mod intcode_profile;
+pub mod intcode_search;
+pub mod intcode_snapshot;
+pub mod intcode_trace;
 pub mod log;
 pub mod point;
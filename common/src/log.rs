@@ -7,6 +7,10 @@ impl Log {
         Self { active }
     }
 
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
     pub fn print<S: AsRef<str>>(&self, msg: S) {
         if self.active {
             print!("{}", msg.as_ref());
@@ -9,13 +9,13 @@ pub trait Max {
 
 impl Max for isize {
     fn max() -> Self {
-        std::isize::MAX
+        isize::MAX
     }
 }
 
 impl Max for usize {
     fn max() -> Self {
-        std::usize::MAX
+        usize::MAX
     }
 }
 
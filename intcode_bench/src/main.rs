@@ -0,0 +1,290 @@
+use anyhow::{anyhow, ensure, Result};
+use common::intcode_comp::*;
+use common::intcode_loader::*;
+use common::intcode_search::*;
+use common::log::*;
+use intcode_baseline::intcode_comp as baseline;
+use intcode_baseline::log as baseline_log;
+use std::env;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Mode {
+    /// Interpreter before the series, built from the `baseline` worktree
+    Baseline,
+    Plain,
+    Cached,
+}
+
+const MODES: [Mode; 3] = [Mode::Baseline, Mode::Plain, Mode::Cached];
+
+/// Program run in the given mode. Returns values compared between modes.
+type Workload = fn(&str, Mode) -> Result<Vec<DataType>>;
+
+/// Operations the workloads need from both interpreters
+trait Machine {
+    fn add_input(&mut self, input: DataType);
+    fn run(&mut self) -> Result<()>;
+    fn exec(&mut self) -> Result<()>;
+    fn reset(&mut self);
+    fn get_mem(&mut self, addr: usize) -> Result<DataType>;
+    fn set_mem(&mut self, addr: usize, value: DataType);
+    fn get_output(&mut self) -> Vec<DataType>;
+    fn is_halted(&self) -> bool;
+}
+
+macro_rules! impl_machine {
+    ($comp:ty, $get_mem:expr) => {
+        impl Machine for $comp {
+            fn add_input(&mut self, input: DataType) {
+                self.add_input(input)
+            }
+
+            fn run(&mut self) -> Result<()> {
+                self.run()
+            }
+
+            fn exec(&mut self) -> Result<()> {
+                self.exec()
+            }
+
+            fn reset(&mut self) {
+                self.reset()
+            }
+
+            fn get_mem(&mut self, addr: usize) -> Result<DataType> {
+                $get_mem(self, addr)
+            }
+
+            fn set_mem(&mut self, addr: usize, value: DataType) {
+                self.set_mem(addr, value)
+            }
+
+            fn get_output(&mut self) -> Vec<DataType> {
+                self.get_output()
+            }
+
+            fn is_halted(&self) -> bool {
+                self.is_halted()
+            }
+        }
+    };
+}
+
+impl_machine!(IntcodeComp<'_>, |comp: &mut IntcodeComp, addr| Ok(
+    IntcodeComp::get_mem(comp, addr)
+));
+impl_machine!(baseline::IntcodeComp<'_>, baseline_get_mem);
+
+/// Baseline has no memory getter. Halted program is continued by `OUT addr; EXIT` placed
+/// after its last instruction.
+fn baseline_get_mem(comp: &mut baseline::IntcodeComp, addr: usize) -> Result<DataType> {
+    ensure!(comp.is_halted(), "ERROR: Memory is read only after halt.");
+
+    let ip = comp.get_ip();
+    comp.set_mem(ip, 4);
+    comp.set_mem(ip + 1, addr as DataType);
+    comp.set_mem(ip + 2, 99);
+    comp.start();
+    comp.run()?;
+
+    comp.get_output()
+        .pop()
+        .ok_or_else(|| anyhow!("ERROR: Memory cell {} was not read.", addr))
+}
+
+fn baseline_log() -> &'static baseline_log::Log {
+    static LOG: OnceLock<baseline_log::Log> = OnceLock::new();
+    LOG.get_or_init(|| baseline_log::Log::new(false))
+}
+
+const WORKLOADS: &[(&str, &str, Workload)] = &[
+    (
+        "day02 noun/verb sweep",
+        include_str!("../../task02_2/input.txt"),
+        day02,
+    ),
+    (
+        "day05 diagnostics",
+        include_str!("../../task05_2/input.txt"),
+        day05,
+    ),
+    (
+        "day07 feedback loop",
+        include_str!("../../task07_2/input.txt"),
+        day07,
+    ),
+    (
+        "day09 BOOST",
+        include_str!("../../task09_1/input.txt"),
+        day09,
+    ),
+    (
+        "day19 50x50 scan",
+        include_str!("../../task19_1/input.txt"),
+        day19,
+    ),
+];
+
+fn main() -> Result<()> {
+    let repeats: u32 = match env::args().nth(1) {
+        Some(arg) => arg.parse()?,
+        None => 5,
+    };
+    ensure!(repeats > 0, "ERROR: Number of repeats should be positive.");
+
+    println!(
+        "{:<24} {:>12} {:>12} {:>12} {:>8}  result",
+        "workload", "baseline, ms", "plain, ms", "cached, ms", "speedup"
+    );
+
+    for (name, prog, workload) in WORKLOADS {
+        let prog = prog.trim();
+        let mut times = Vec::new();
+        let mut expected = None;
+
+        for mode in &MODES {
+            let (result, time) = measure(repeats, || workload(prog, *mode))?;
+            let expected = expected.get_or_insert_with(|| result.clone());
+            ensure!(
+                result == *expected,
+                "ERROR: {} results differ. Baseline: {:?}, {:?}: {:?}",
+                name,
+                expected,
+                mode,
+                result
+            );
+            times.push(time.as_secs_f64());
+        }
+
+        println!(
+            "{:<24} {:>12.3} {:>12.3} {:>12.3} {:>7.2}x  {:?}",
+            name,
+            times[0] * 1000.0,
+            times[1] * 1000.0,
+            times[2] * 1000.0,
+            times[0] / times[2],
+            expected.unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+/// Best time of `repeats` runs
+fn measure<F>(repeats: u32, mut run: F) -> Result<(Vec<DataType>, Duration)>
+where
+    F: FnMut() -> Result<Vec<DataType>>,
+{
+    let mut best = Duration::MAX;
+    let mut result = Vec::new();
+
+    for _ in 0..repeats {
+        let start = Instant::now();
+        result = run()?;
+        best = best.min(start.elapsed());
+    }
+
+    Ok((result, best))
+}
+
+fn comp<'l>(prog: &str, mode: Mode, log: &'l Log) -> Result<Box<dyn Machine + 'l>> {
+    let prog = parse_prog(prog)?;
+
+    Ok(match mode {
+        Mode::Baseline => Box::new(baseline::IntcodeComp::new(prog, baseline_log())),
+        Mode::Plain | Mode::Cached => {
+            let mut comp = IntcodeComp::new(prog, log);
+            comp.set_decode_cache(mode == Mode::Cached);
+            Box::new(comp)
+        }
+    })
+}
+
+fn run_with_input(comp: &mut dyn Machine, input: DataType) -> Result<Vec<DataType>> {
+    comp.add_input(input);
+    comp.run()?;
+    Ok(comp.get_output())
+}
+
+fn day02(prog: &str, mode: Mode) -> Result<Vec<DataType>> {
+    let log = Log::new(false);
+    let mut comp = comp(prog, mode, &log)?;
+    let mut results = Vec::new();
+
+    for noun in 0..100 {
+        for verb in 0..100 {
+            comp.reset();
+            comp.set_mem(1, noun);
+            comp.set_mem(2, verb);
+            comp.exec()?;
+
+            if comp.get_mem(0)? == 19_690_720 {
+                results.push(100 * noun + verb);
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+fn day05(prog: &str, mode: Mode) -> Result<Vec<DataType>> {
+    let log = Log::new(false);
+    let mut comp = comp(prog, mode, &log)?;
+    comp.add_input(5);
+    comp.exec()?;
+    Ok(comp.get_output())
+}
+
+fn day07(prog: &str, mode: Mode) -> Result<Vec<DataType>> {
+    let log = Log::new(false);
+    let mut best = None;
+
+    for phases in ParamSpace::Permutations(vec![5, 6, 7, 8, 9]).candidates() {
+        let mut amps = phases
+            .iter()
+            .map(|&phase| {
+                let mut amp = comp(prog, mode, &log)?;
+                amp.add_input(phase);
+                Ok(amp)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut signal = 0;
+        while !amps[amps.len() - 1].is_halted() {
+            for amp in amps.iter_mut() {
+                signal = *run_with_input(amp.as_mut(), signal)?
+                    .last()
+                    .ok_or_else(|| anyhow!("ERROR: Amplifier produced no output."))?;
+            }
+        }
+
+        best = best.max(Some(signal));
+    }
+
+    Ok(best.into_iter().collect())
+}
+
+fn day09(prog: &str, mode: Mode) -> Result<Vec<DataType>> {
+    let log = Log::new(false);
+    let mut comp = comp(prog, mode, &log)?;
+    run_with_input(comp.as_mut(), 2)
+}
+
+fn day19(prog: &str, mode: Mode) -> Result<Vec<DataType>> {
+    let log = Log::new(false);
+    let mut pulled = 0;
+
+    for y in 0..50 {
+        for x in 0..50 {
+            let mut comp = comp(prog, mode, &log)?;
+            comp.add_input(x);
+            comp.add_input(y);
+            comp.exec()?;
+            pulled += comp.get_output().iter().sum::<DataType>();
+        }
+    }
+
+    Ok(vec![pulled])
+}
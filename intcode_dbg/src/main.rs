@@ -0,0 +1,344 @@
+use anyhow::{anyhow, bail, Result};
+use common::intcode_analysis::*;
+use common::intcode_comp::*;
+use common::intcode_debug::*;
+use common::intcode_diff::*;
+use common::intcode_disasm::*;
+use common::intcode_loader::*;
+use common::intcode_memory::*;
+use common::log::*;
+use std::env;
+use std::fs;
+use std::io::{self, prelude::*};
+
+const HELP: &str = "Commands:
+  s [n]               execute n instructions (default 1)
+  c                   continue until breakpoint, watchpoint, input request or halt
+  b <ip>              add breakpoint
+  db <ip>             delete breakpoint
+  w <addr> [r|w|rw]   add watchpoint (default w)
+  dw <addr>           delete watchpoint
+  l                   list breakpoints and watchpoints
+  x <addr> [n]        show n memory cells (default 1)
+  p <addr> <value>    patch memory cell
+  d [addr] [n]        disassemble n instructions starting from addr (default ip)
+  a [file]            analyze memory: summary, or save CFG to file.dot / summary to file.json
+  m                   mark memory state
+  md                  show memory changes since mark
+  v [addr] [name]     watch memory cell value, list watched cells without arguments
+  dv <addr>           stop watching memory cell
+  i <v1> [v2 ...]     add values to the input queue
+  t <text>            add ASCII text with trailing newline to the input queue
+  o                   show and clear output queue
+  r                   show registers and queues
+  reset               reload program
+  q                   quit";
+
+/// Memory views of the session
+#[derive(Default)]
+struct View {
+    mark: Option<MemoryImage>,
+    watch: WatchList,
+}
+
+fn main() -> Result<()> {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "input.txt".to_string());
+    let log = Log::new(false);
+    let comp = IntcodeComp::new(read_prog_file(&path)?, &log);
+
+    let mut dbg = Debugger::new(comp);
+    let mut view = View::default();
+
+    println!("Loaded {}. Type 'h' for help.", path);
+    show_next(&dbg);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        match exec_cmd(&mut dbg, &mut view, line.trim()) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("Error: {}", err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns false on quit
+fn exec_cmd(dbg: &mut Debugger, view: &mut View, line: &str) -> Result<bool> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+
+    if args.is_empty() {
+        return Ok(true);
+    }
+
+    match args[0] {
+        "h" | "help" => println!("{}", HELP),
+        "q" | "quit" => return Ok(false),
+        "s" | "step" => {
+            let count = parse_arg(&args, 1)?.unwrap_or(1);
+            for _ in 0..count {
+                let reason = dbg.step()?;
+                if let Some(event) = dbg.last_event()? {
+                    println!("{}", event);
+                }
+                if reason != StopReason::Step {
+                    show_reason(&reason);
+                    break;
+                }
+            }
+            show_watch(dbg, view);
+            show_next(dbg);
+        }
+        "c" | "cont" => {
+            let reason = dbg.cont()?;
+            show_reason(&reason);
+            show_watch(dbg, view);
+            show_next(dbg);
+        }
+        "b" => {
+            let ip = require_arg(&args, 1)?;
+            dbg.add_breakpoint(ip);
+        }
+        "db" => {
+            let ip = require_arg(&args, 1)?;
+            if !dbg.remove_breakpoint(ip) {
+                println!("No breakpoint at {}", ip);
+            }
+        }
+        "w" => {
+            let addr = require_arg(&args, 1)?;
+            let kind = match args.get(2).cloned().unwrap_or("w") {
+                "r" => WatchKind::Read,
+                "w" => WatchKind::Write,
+                "rw" => WatchKind::Access,
+                kind => bail!("Unknown watchpoint kind '{}'", kind),
+            };
+            dbg.add_watchpoint(addr, kind);
+        }
+        "dw" => {
+            let addr = require_arg(&args, 1)?;
+            if !dbg.remove_watchpoint(addr) {
+                println!("No watchpoint at {}", addr);
+            }
+        }
+        "l" => {
+            println!("Breakpoints: {:?}", dbg.breakpoints());
+            println!("Watchpoints: {:?}", dbg.watchpoints());
+        }
+        "x" => {
+            let addr: usize = require_arg(&args, 1)?;
+            let count: usize = parse_arg(&args, 2)?.unwrap_or(1);
+            for a in addr..addr.saturating_add(count) {
+                println!("[{:4}] {}", a, dbg.comp().get_mem(a));
+            }
+        }
+        "p" => {
+            let addr = require_arg(&args, 1)?;
+            let value = require_arg(&args, 2)?;
+            dbg.comp_mut().set_mem(addr, value);
+        }
+        "d" => {
+            let addr = parse_arg(&args, 1)?.unwrap_or_else(|| dbg.comp().get_ip());
+            let count = parse_arg(&args, 2)?.unwrap_or(10);
+            if addr > dbg.comp().get_max_addr() {
+                bail!("Address {} exceeds memory limit", addr);
+            }
+            show_disasm(dbg, addr, count);
+        }
+        "a" => {
+            let memory = dbg.comp().memory();
+            if memory.size() > DEFAULT_MAX_ADDR + 1 {
+                bail!("Memory of {} cells is too large to analyze", memory.size());
+            }
+            let analysis = analyze(&memory.to_vec());
+            match args.get(1) {
+                Some(path) if path.ends_with(".dot") => fs::write(path, analysis.to_dot())?,
+                Some(path) => fs::write(path, analysis.to_json()?)?,
+                None => show_analysis(&analysis),
+            }
+        }
+        "m" => view.mark = Some(dbg.comp().memory()),
+        "md" => {
+            let mark = view
+                .mark
+                .as_ref()
+                .ok_or_else(|| anyhow!("Memory is not marked"))?;
+            let diff = MemoryDiff::since(mark, dbg.comp());
+            println!(
+                "{} cells changed in {} ranges",
+                diff.len(),
+                diff.ranges().len()
+            );
+            print!("{}", diff);
+        }
+        "v" => match parse_arg::<usize>(&args, 1)? {
+            Some(addr) => {
+                let name = args
+                    .get(2)
+                    .map_or_else(|| addr.to_string(), |n| n.to_string());
+                view.watch.add(dbg.comp(), addr, &name);
+            }
+            None => print!("{}", view.watch),
+        },
+        "dv" => {
+            let addr = require_arg(&args, 1)?;
+            if !view.watch.remove(addr) {
+                println!("Cell {} is not watched", addr);
+            }
+        }
+        "i" => {
+            if args.len() < 2 {
+                bail!("Expected input values");
+            }
+            for arg in &args[1..] {
+                dbg.comp_mut().add_input(arg.parse()?);
+            }
+        }
+        "t" => {
+            let text = line[1..].trim_start();
+            for c in text.chars().chain(std::iter::once('\n')) {
+                dbg.comp_mut().add_input(c as DataType);
+            }
+        }
+        "o" => {
+            let output = dbg.comp_mut().get_output();
+            println!("Output: {:?}", output);
+            if !output.is_empty() && output.iter().all(|v| *v == 10 || (32..127).contains(v)) {
+                let text: String = output.iter().map(|v| *v as u8 as char).collect();
+                println!("{}", text);
+            }
+        }
+        "r" => {
+            let comp = dbg.comp();
+            println!(
+                "ip={} rel_base={} status={:?} steps={}",
+                comp.get_ip(),
+                comp.get_rel_base(),
+                comp.get_status(),
+                comp.get_steps()
+            );
+            println!("Input:  {:?}", comp.input());
+            println!("Output: {:?}", comp.output());
+        }
+        "reset" => {
+            dbg.comp_mut().reset();
+            show_next(dbg);
+        }
+        cmd => bail!("Unknown command '{}'. Type 'h' for help.", cmd),
+    }
+
+    Ok(true)
+}
+
+fn show_analysis(analysis: &Analysis) {
+    let summary = analysis.summary();
+    println!(
+        "Size: {}, code: {}, data: {}, blocks: {}, edges: {}",
+        summary.size, summary.code_cells, summary.data_cells, summary.blocks, summary.edges
+    );
+    for func in &summary.functions {
+        println!(
+            "Function {}: frame {:?}, blocks {}, called from {:?}, calls {:?}",
+            func.entry,
+            func.frame_size,
+            func.blocks.len(),
+            func.call_sites,
+            func.calls
+        );
+    }
+    for m in &summary.self_modifications {
+        println!(
+            "Self-modification: [{}] writes [{}] of instruction [{}]",
+            m.ip, m.addr, m.target_ip
+        );
+    }
+    for range in &summary.unreachable {
+        println!("Unreachable code: {}..{}", range.start, range.end);
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[&str], idx: usize) -> Result<Option<T>> {
+    match args.get(idx) {
+        Some(arg) => Ok(Some(
+            arg.parse()
+                .map_err(|_| anyhow!("Wrong argument '{}'", arg))?,
+        )),
+        None => Ok(None),
+    }
+}
+
+fn require_arg<T: std::str::FromStr>(args: &[&str], idx: usize) -> Result<T> {
+    parse_arg(args, idx)?.ok_or_else(|| anyhow!("Missing argument {}", idx))
+}
+
+fn show_reason(reason: &StopReason) {
+    match reason {
+        StopReason::Step => {}
+        StopReason::Breakpoint(ip) => println!("Breakpoint at {}", ip),
+        StopReason::Watchpoint {
+            ip,
+            addr,
+            value,
+            write,
+        } => println!(
+            "Watchpoint: [{}] {} {} by instruction at {}",
+            addr,
+            if *write { "<-" } else { "->" },
+            value,
+            ip
+        ),
+        StopReason::WaitForInput => println!("Waiting for input"),
+        StopReason::Halted => println!("Program halted"),
+        StopReason::Interrupted(interrupt) => println!("Interrupted: {:?}", interrupt),
+    }
+}
+
+fn show_disasm(dbg: &Debugger, addr: usize, count: usize) {
+    let comp = dbg.comp();
+    let mem: Vec<DataType> = (0..comp.mem_size().max(addr + 1))
+        .map(|a| comp.get_mem(a))
+        .collect();
+    let mut disasm = Disassembler::new(&mem);
+    disasm.add_entry_point(addr);
+    let listing = disasm.disassemble();
+    let start = listing
+        .entries()
+        .iter()
+        .position(|e| e.addr() + e.size() > addr)
+        .unwrap_or(0);
+
+    for entry in listing.entries()[start..].iter().take(count) {
+        let marker = if entry.addr() == comp.get_ip() {
+            "=>"
+        } else {
+            "  "
+        };
+        println!("{}{}", marker, entry);
+        for overlap in listing.overlaps_in(entry) {
+            println!("  {}", overlap);
+        }
+    }
+}
+
+fn show_watch(dbg: &Debugger, view: &mut View) {
+    for (watch, old) in view.watch.update(dbg.comp()) {
+        println!("{}: {} -> {}", watch.name, old, watch.value);
+    }
+}
+
+fn show_next(dbg: &Debugger) {
+    if !dbg.comp().is_halted() {
+        show_disasm(dbg, dbg.comp().get_ip(), 1);
+    }
+}
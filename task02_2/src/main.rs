@@ -1,4 +1,5 @@
 use anyhow::{bail, Result};
+use common::intcode_search::*;
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
 
@@ -10,15 +11,16 @@ fn main() -> Result<()> {
         let commands = cmd_str?;
         println!("Input: {}", commands);
 
-        'outer: for noun in 0..100 {
-            for verb in 0..100 {
-                let res = process(noun, verb, &commands)?;
+        let space = ParamSpace::Product(vec![ParamSpace::Range(0..100), ParamSpace::Range(0..100)]);
+        let found = search(commands.as_str(), &space, |commands, params| {
+            // Noun and verb pointing outside of the program are not solutions
+            let res = process(params[0] as u32, params[1] as u32, commands).ok();
+            Ok(res.filter(|r| *r == 19_690_720).map(|_| 0))
+        })?;
 
-                if res == 19_690_720 {
-                    println!("Result: {}", 100 * noun + verb);
-                    break 'outer;
-                }
-            }
+        if let Some(found) = found {
+            let params = &found.best[0];
+            println!("Result: {}", 100 * params[0] + params[1]);
         }
     } else {
         bail!("ERROR: Cannot read command.");
@@ -1,17 +1,10 @@
-use crate::intcode_comp::*;
-use anyhow::{anyhow, ensure, Result};
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
-
-mod intcode_comp;
+use anyhow::{ensure, Result};
+use common::intcode_comp::*;
+use common::intcode_loader::*;
+use common::log::*;
 
 fn main() -> Result<()> {
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
+    let prog_str = read_prog_text("input.txt")?;
 
     let mut phase_settings = vec![0, 1, 2, 3, 4];
     let mut output = run_amplifier(&prog_str, &phase_settings)?;
@@ -29,7 +22,7 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn next_set(values: &mut Vec<i32>) -> bool {
+fn next_set(values: &mut [DataType]) -> bool {
     let mut i = (values.len() - 2) as i32;
 
     while i >= 0 && values[i as usize] > values[(i + 1) as usize] {
@@ -59,19 +52,18 @@ fn next_set(values: &mut Vec<i32>) -> bool {
     true
 }
 
-fn run_amplifier(prog_str: &str, phase_settings: &[i32]) -> Result<i32> {
+fn run_amplifier(prog_str: &str, phase_settings: &[DataType]) -> Result<DataType> {
     let steps = phase_settings.len();
     ensure!(steps > 0, "ERROR: No phase settings are set.");
 
+    let log = Log::new(false);
     let mut result = 0;
-    let mut comps = Vec::new();
 
-    for _i in 0..steps {
-        comps.push(IntcodeComp::new(prog_str)?);
-    }
+    for (i, phase) in phase_settings.iter().enumerate() {
+        let mut comp = IntcodeComp::with_phase(Vec::new(), *phase, &log);
+        comp.load_prog(prog_str)?;
 
-    for i in 0..steps {
-        let output = comps[i].run(vec![phase_settings[i], result])?;
+        let output = comp.run_with_input(result)?;
         ensure!(
             output.len() == 1,
             "ERROR: Comp {}. Expected 1 output but was {}.",
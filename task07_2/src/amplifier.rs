@@ -1,61 +1,46 @@
-use crate::intcode_comp::*;
-use crate::log::*;
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
+use common::intcode_comp::*;
+use common::intcode_graph::*;
+use common::intcode_loader::*;
+use common::log::*;
 
 pub struct Amplifier<'a> {
-    prog: Vec<i32>,
-    comps: Vec<IntcodeComp<'a>>,
+    prog: Vec<DataType>,
     log: &'a Log,
 }
 
 impl<'a> Amplifier<'a> {
     pub fn new(commands: &str, log: &'a Log) -> Result<Self> {
-        let mut result = Self {
-            prog: Vec::new(),
-            comps: Vec::new(),
+        Ok(Self {
+            prog: parse_prog(commands)?,
             log,
-        };
-
-        result.parse(commands)?;
-
-        Ok(result)
+        })
     }
 
-    pub fn run(&mut self, phase_settings: &[i32]) -> Result<i32> {
+    pub fn run(&mut self, phase_settings: &[DataType]) -> Result<DataType> {
         self.log.println(format!("Commands: {}", self.prog.len()));
         self.log.println(format!("Phases: {:?}", phase_settings));
 
         let steps = phase_settings.len();
         ensure!(steps > 0, "ERROR: No phase settings are set.");
 
-        self.comps.clear();
+        let mut builder = GraphBuilder::new();
+        let mut nodes = Vec::new();
 
-        for phase in phase_settings {
-            self.comps
-                .push(IntcodeComp::new(self.prog.clone(), *phase, self.log));
+        for (i, phase) in phase_settings.iter().enumerate() {
+            let node = builder.add_node(format!("Amp{}", i), self.prog.clone());
+            builder.add_input(node, *phase)?;
+            nodes.push(node);
         }
 
-        let mut result = 0;
+        builder.add_input(nodes[0], 0)?;
+        builder.ring(&nodes)?;
 
-        let mut k = 0;
-        while !self.comps[self.comps.len() - 1].is_halted() {
-            self.log.println(format!("Iteration {}", k));
-            k += 1;
-
-            for i in 0..steps {
-                self.log.println(format!("  Comp: {}", i));
-                result = self.comps[i].run(result)?;
-            }
-        }
+        let mut graph = builder.build(self.log);
+        graph.run()?;
 
-        Ok(result)
-    }
-
-    fn parse(&mut self, commands: &str) -> Result<()> {
-        let cmd_str: Vec<&str> = commands.split(',').collect();
-        for cmd in cmd_str {
-            self.prog.push(cmd.parse()?);
-        }
-        Ok(())
+        graph
+            .last_output(nodes[steps - 1])
+            .ok_or_else(|| anyhow!("ERROR: Last amplifier produced no output."))
     }
 }
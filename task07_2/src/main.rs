@@ -1,69 +1,28 @@
 use crate::amplifier::*;
-use crate::log::*;
 use anyhow::{anyhow, Result};
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use common::intcode_loader::*;
+use common::intcode_search::*;
+use common::log::*;
 
 mod amplifier;
-pub mod intcode_comp;
-pub mod log;
 
 fn main() -> Result<()> {
-    let log = Log::new(false);
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
+    let prog_str = read_prog_text("input.txt")?;
 
-    let mut phase_settings = vec![5, 6, 7, 8, 9];
-    let mut amplifier = Amplifier::new(&prog_str, &log)?;
-    let mut output = amplifier.run(&phase_settings)?;
-
-    while next_set(&mut phase_settings) {
-        let o = amplifier.run(&phase_settings)?;
-
-        if o > output {
-            output = o;
-        }
-    }
+    let space = ParamSpace::Permutations(vec![5, 6, 7, 8, 9]);
+    let output = search(prog_str.as_str(), &space, |prog, phases| {
+        let log = Log::new(false);
+        let mut amplifier = Amplifier::new(prog, &log)?;
+        Ok(Some(amplifier.run(phases)?))
+    })?
+    .ok_or_else(|| anyhow!("ERROR: No phase settings were checked."))?
+    .score;
 
     println!("Output: {}", output);
 
     Ok(())
 }
 
-fn next_set(values: &mut Vec<i32>) -> bool {
-    let mut i = (values.len() - 2) as i32;
-
-    while i >= 0 && values[i as usize] > values[(i + 1) as usize] {
-        i -= 1;
-    }
-
-    if i < 0 {
-        return false;
-    }
-
-    let mut j = i + 1;
-    let mut k = (values.len() - 1) as i32;
-
-    while j < k {
-        values.swap(j as usize, k as usize);
-        j += 1;
-        k -= 1;
-    }
-
-    j = i + 1;
-    while values[j as usize] < values[i as usize] {
-        j += 1;
-    }
-
-    values.swap(i as usize, j as usize);
-
-    true
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,12 +42,12 @@ mod tests {
     }
 
     #[test]
-    fn test4() -> Result<()> {
-        let mut data = vec![0, 1, 2];
+    fn test_trailing_newline() -> Result<()> {
+        let log = Log::new(false);
+        let prog_str = "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5\n";
+        let mut amplifier = Amplifier::new(prog_str, &log)?;
 
-        while next_set(&mut data) {
-            println!("{:?}", data);
-        }
+        assert_eq!(139629729, amplifier.run(&[9, 8, 7, 6, 5])?);
 
         Ok(())
     }
@@ -1,7 +1,7 @@
-use crate::intcode_comp::*;
-use crate::log::*;
 use crate::utils::*;
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
+use common::intcode_comp::*;
+use common::log::*;
 use std::collections::HashMap;
 use std::io::Write;
 use std::{thread, time};
@@ -93,24 +93,27 @@ impl<'l> Robot<'l> {
 
             self.comp.add_input(cur_color as DataType);
 
-            let new_color = self.comp.run()?;
+            let new_color = match self.comp.run_until_output()? {
+                Some(color) => color,
+                None if self.comp.is_halted() => break,
+                None => return Err(anyhow!("Robot program stopped without painting")),
+            };
             ensure!(
                 new_color == 0 || new_color == 1,
                 "Wrong color. Expected 0|1 but was {}",
                 new_color
             );
 
-            if self.comp.is_halted() {
-                break;
-            }
-
             self.log.println(format!("  New color: {}", new_color));
 
             self.set_color(new_color as u8);
 
             painted_panels.insert(self.position.clone(), 0);
 
-            let turn = self.comp.run()?;
+            let turn = self
+                .comp
+                .run_until_output()?
+                .ok_or_else(|| anyhow!("Robot program stopped without turning"))?;
             ensure!(
                 turn == 0 || turn == 1,
                 "Wrong turn. Expected 0|1 but was {}",
@@ -47,6 +47,10 @@ impl<'l> Arcade<'l> {
         }
     }
 
+    pub fn comp_mut(&mut self) -> &mut IntcodeComp<'l> {
+        &mut self.comp
+    }
+
     pub fn get_tiles_by_id(&self, id: TileType) -> usize {
         let mut result = 0;
         for row in &self.screen {
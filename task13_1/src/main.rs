@@ -1,25 +1,21 @@
 use crate::arcade::*;
-use anyhow::{anyhow, Result};
-use common::intcode_comp::*;
+use anyhow::Result;
+use common::intcode_loader::*;
 use common::log::*;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use std::env;
 
 pub mod arcade;
 
 fn main() -> Result<()> {
     let log = Log::new(false);
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
-
-    let prog = parse_prog(prog_str)?;
+    let prog = read_prog_file("input.txt")?;
     println!("Prog len: {}", prog.len());
     log.println(format!("Prog: {:?}", prog));
-    let mut arcade = Arcade::new(prog, true, &log);
+    let profile = env::args().any(|arg| arg == "--profile");
+    let mut arcade = Arcade::new(prog, !profile, &log);
+    if profile {
+        arcade.comp_mut().start_profiling();
+    }
 
     arcade.build_map()?;
     let blocks_count = arcade.get_tiles_by_id(TileType::Block);
@@ -29,14 +25,11 @@ fn main() -> Result<()> {
     println!("Blocks count: {:?}", blocks_count);
     println!("Score: {}", score);
 
-    Ok(())
-}
-
-fn parse_prog<S: AsRef<str>>(commands: S) -> Result<Vec<DataType>> {
-    let cmd_str: Vec<&str> = commands.as_ref().split(',').collect();
-    let mut prog: Vec<DataType> = Vec::new();
-    for cmd in cmd_str {
-        prog.push(cmd.parse()?);
+    if let Some(profile) = arcade.comp_mut().stop_profiling() {
+        println!("{}", profile.report(10));
+        profile.save_folded("profile.folded")?;
+        println!("Folded stacks are saved to profile.folded");
     }
-    Ok(prog)
+
+    Ok(())
 }
@@ -1,5 +1,6 @@
-use anyhow::{ensure, Result};
+use anyhow::{anyhow, ensure, Result};
 use common::dynamic_map::*;
+use common::intcode_ascii::*;
 use common::intcode_comp::*;
 use common::log::*;
 use common::point::*;
@@ -20,44 +21,34 @@ impl Default for Cell {
     }
 }
 
-impl From<DataType> for Cell {
-    fn from(d: DataType) -> Self {
-        Self(d as u8 as char)
-    }
-}
-
 pub struct Robot<'l> {
-    comp: IntcodeComp<'l>,
+    comp: AsciiComp<'l>,
     map: DynamicMap<Cell>,
 }
 
 impl<'l> Robot<'l> {
     pub fn new(prog: &str, log: &'l Log) -> Result<Self> {
-        let mut comp = IntcodeComp::new(Vec::new(), log);
-        comp.load_prog(prog)?;
         let res = Self {
-            comp,
+            comp: AsciiComp::from_prog(prog, log)?,
             map: DynamicMap::new(),
         };
         Ok(res)
     }
 
     pub fn camera_scan(&mut self) -> Result<()> {
-        self.comp.exec()?;
-        ensure!(self.comp.is_halted(), "IntCode should be halted");
+        let output = self.comp.exec()?;
+        ensure!(self.comp.comp().is_halted(), "IntCode should be halted");
 
-        let output = self.comp.get_output();
-
-        for ch in output {
+        for ch in output.text.chars() {
             // println!("ch: {}", ch);
-            if ch == 10 {
+            if ch == '\n' {
                 // println!("  south front");
                 self.map.move_south_front();
             } else {
                 // let abs_pos = self.map.abs_position();
                 // let size = self.map.size();
                 // println!("  pos: {:?} size: {:?}", abs_pos, size);
-                self.map.set_cell(ch.into());
+                self.map.set_cell(Cell(ch));
                 self.map.do_move(&Direction::East);
             }
         }
@@ -87,8 +78,8 @@ impl<'l> Robot<'l> {
     }
 
     pub fn wake_up(&mut self) {
-        self.comp.set_mem(0, 2);
-        self.comp.start();
+        self.comp.comp_mut().set_mem(0, 2);
+        self.comp.comp_mut().start();
     }
 
     pub fn move_robot(
@@ -98,38 +89,19 @@ impl<'l> Robot<'l> {
         func_b: &str,
         func_c: &str,
     ) -> Result<DataType> {
-        self.comp.add_input_vec(&mut Self::str2input(routine));
-        self.comp.add_input_vec(&mut Self::str2input(func_a));
-        self.comp.add_input_vec(&mut Self::str2input(func_b));
-        self.comp.add_input_vec(&mut Self::str2input(func_c));
-        self.comp.add_input_vec(&mut Self::str2input("n"));
-
-        self.comp.exec()?;
-
-        let output = self.comp.get_output();
+        self.comp
+            .send_lines(&[routine, func_a, func_b, func_c, "n"]);
 
-        // println!("Output: {:?}", output);
+        let output = self.comp.exec()?;
 
-        // for c in output {
-        //     if c == 10 {
-        //         println!();
-        //     } else {
-        //         print!("{}", c as u8 as char);
-        //     }
-        // }
+        // println!("Output:\n{}", output.text);
 
-        Ok(output[output.len() - 1])
+        output
+            .value()
+            .ok_or_else(|| anyhow!("Robot did not report collected dust"))
     }
 
     pub fn show(&self) -> Result<()> {
         self.map.show(&mut io::stdout())
     }
-
-    fn str2input(data: &str) -> Vec<DataType> {
-        let mut result: Vec<DataType> = data.chars().map(|c| c as u8 as DataType).collect();
-
-        result.push(10);
-
-        result
-    }
 }
@@ -1,19 +1,13 @@
 use crate::drone::*;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use common::intcode_loader::*;
 use common::log::*;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
 
 mod drone;
 
 fn main() -> Result<()> {
     let log = Log::new(false);
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
+    let prog_str = read_prog_text("input.txt")?;
 
     let mut drone = Drone::new(&prog_str, &log)?;
 
@@ -1,19 +1,13 @@
 use crate::spring_droid::*;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use common::intcode_loader::*;
 use common::log::*;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
 
 mod spring_droid;
 
 fn main() -> Result<()> {
     let log = Log::new(false);
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
+    let prog_str = read_prog_text("input.txt")?;
 
     let mut droid = SpringDroid::new(&prog_str, &log)?;
 
@@ -27,11 +21,14 @@ fn main() -> Result<()> {
     // )?;
 
     // Task 2
-    let dust_count = droid.move_droid(&[
+    let (text, dust_count) = droid.move_droid(&[
         "OR B J", "AND C J", "NOT J J", "AND D J", "AND H J", "NOT A T", "OR T J", "RUN",
     ])?;
 
-    println!("Dust count: {}", dust_count);
+    match dust_count {
+        Some(dust_count) => println!("Dust count: {}", dust_count),
+        None => println!("Output:\n{}", text),
+    }
 
     // let comb1 = vec![
     //     "AND A T", "AND A J", "AND B T", "AND B J", "AND C T", "AND C J", "AND D T", "AND D J", "AND T T", "AND T J", "AND J T", "AND J J",
@@ -1,21 +1,14 @@
-use crate::network::*;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use common::intcode_loader::*;
+use common::intcode_network::*;
 use common::log::*;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
-
-mod network;
 
 fn main() -> Result<()> {
     let log = Log::new(false);
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
+    let prog_str = read_prog_text("input.txt")?;
 
     let mut network = Network::new(&prog_str, 50, &log)?;
+    network.add_hook(255, Box::new(FirstPacketMonitor));
 
     let y = network.run()?;
 
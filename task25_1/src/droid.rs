@@ -1,6 +1,8 @@
 use anyhow::{bail, ensure, Result};
 use common::dynamic_map::*;
-use common::intcode_comp::*;
+use common::intcode_ascii::*;
+use common::intcode_diff::*;
+use common::intcode_memory::*;
 use common::log::*;
 use common::point::*;
 use std::fmt;
@@ -107,34 +109,56 @@ impl OutputData {
 }
 
 pub struct Droid<'l> {
-    comp: IntcodeComp<'l>,
+    comp: AsciiComp<'l>,
     map: DynamicMap<Cell>,
+    show_memory: bool,
+    /// Memory at the previous command prompt
+    mem: MemoryImage,
 }
 
 impl<'l> Droid<'l> {
     pub fn new(prog: &str, log: &'l Log) -> Result<Self> {
-        let mut comp = IntcodeComp::new(Vec::new(), log);
-
-        comp.load_prog(prog)?;
+        let comp = AsciiComp::from_prog(prog, log)?;
+        let mem = comp.comp().memory();
 
         Ok(Self {
             comp,
             map: DynamicMap::new(),
+            show_memory: false,
+            mem,
         })
     }
 
-    fn run_iter(&mut self) -> Result<OutputData> {
-        self.comp.run()?;
+    /// Show memory cells changed by every command, helps to find room and inventory variables
+    pub fn set_show_memory(&mut self, show_memory: bool) {
+        self.show_memory = show_memory;
+    }
+
+    fn memory_changes(&mut self) -> String {
+        if !self.show_memory {
+            return String::new();
+        }
+
+        let diff = MemoryDiff::since(&self.mem, self.comp.comp());
+        self.mem = self.comp.comp().memory();
 
-        let output = self.comp.get_output();
-        let output_str = Self::output2str(&output);
-        let output_vec: Vec<&str> = output_str.trim().lines().map(|l| l.trim()).collect();
+        format!("\nMemory: {} cells changed\n{}", diff.len(), diff)
+    }
+
+    fn run_iter(&mut self) -> Result<OutputData> {
+        let output = self.comp.run()?;
+        let output_vec: Vec<&str> = output.text.trim().lines().map(|l| l.trim()).collect();
 
-        // println!("Output:\n{}", output_str);
+        // println!("Output:\n{}", output.text);
 
         Ok(OutputData::new(&output_vec)?)
     }
 
+    /// Plain text game in the terminal
+    pub fn text_mode(&mut self) -> Result<()> {
+        self.comp.interactive()
+    }
+
     pub fn interactive(&mut self) -> Result<()> {
         let mut stdout = io::stdout().into_raw_mode()?;
         let mut stdin = io::stdin().keys();
@@ -173,8 +197,9 @@ impl<'l> Droid<'l> {
                 self.map.set_cell(Cell::Item(output.items[0].clone()));
             }
 
-            self.show(&mut stdout, &format!("{}\nDoors: {:?}\nItems: {:?}\nInventory: {:?}",
-                output.text, output.doors, output.items, inventory))?;
+            let memory = self.memory_changes();
+            self.show(&mut stdout, &format!("{}\nDoors: {:?}\nItems: {:?}\nInventory: {:?}{}",
+                output.text, output.doors, output.items, inventory, memory))?;
             stdout.lock().flush()?;
 
             // println!("[{}] Doors: {:?} Items: {:?}", output.text, output.doors, output.items);
@@ -195,7 +220,7 @@ impl<'l> Droid<'l> {
                     correct_action = match c? {
                         Key::Up => {
                             if output.doors.iter().any(|d| *d == Direction::North) {
-                                self.comp.add_input_vec(&mut Self::str2input("north"));
+                                self.comp.send_line("north");
                                 self.map.do_move(&Direction::North);
                                 prev_dir = Direction::North;
                                 true
@@ -205,7 +230,7 @@ impl<'l> Droid<'l> {
                         }
                         Key::Down => {
                             if output.doors.iter().any(|d| *d == Direction::South) {
-                                self.comp.add_input_vec(&mut Self::str2input("south"));
+                                self.comp.send_line("south");
                                 self.map.do_move(&Direction::South);
                                 prev_dir = Direction::South;
                                 true
@@ -215,7 +240,7 @@ impl<'l> Droid<'l> {
                         }
                         Key::Left => {
                             if output.doors.iter().any(|d| *d == Direction::West) {
-                                self.comp.add_input_vec(&mut Self::str2input("west"));
+                                self.comp.send_line("west");
                                 self.map.do_move(&Direction::West);
                                 prev_dir = Direction::West;
                                 true
@@ -225,7 +250,7 @@ impl<'l> Droid<'l> {
                         }
                         Key::Right => {
                             if output.doors.iter().any(|d| *d == Direction::East) {
-                                self.comp.add_input_vec(&mut Self::str2input("east"));
+                                self.comp.send_line("east");
                                 self.map.do_move(&Direction::East);
                                 prev_dir = Direction::East;
                                 true
@@ -234,7 +259,7 @@ impl<'l> Droid<'l> {
                             }
                         }
                         Key::Char('i') => {
-                            self.comp.add_input_vec(&mut Self::str2input("inv"));
+                            self.comp.send_line("inv");
                             prev_doors = output.doors.clone();
                             prev_items = output.items.clone();
                             prev_inv = true;
@@ -245,7 +270,7 @@ impl<'l> Droid<'l> {
                                 let item = output.items.remove(0);
                                 let take = format!("take {}", item);
                                 inventory.push(item);
-                                self.comp.add_input_vec(&mut Self::str2input(&take));
+                                self.comp.send_line(&take);
                                 self.map.set_cell(Cell::Empty);
                                 prev_doors = output.doors.clone();
                                 prev_items = output.items.clone();
@@ -287,7 +312,7 @@ impl<'l> Droid<'l> {
                             if let Some(idx) = inv_idx {
                                 let item = inventory.remove(idx);
                                 let drop = format!("drop {}", item);
-                                self.comp.add_input_vec(&mut Self::str2input(&drop));
+                                self.comp.send_line(&drop);
                                 self.map.set_cell(Cell::Item(drop));
                                 prev_doors = output.doors.clone();
                                 prev_items = output.items.clone();
@@ -335,26 +360,4 @@ impl<'l> Droid<'l> {
 
         Ok(())
     }
-
-    fn str2input(data: &str) -> Vec<DataType> {
-        let mut result: Vec<DataType> = data.chars().map(|c| c as u8 as DataType).collect();
-
-        result.push(10);
-
-        result
-    }
-
-    fn output2str(data: &[DataType]) -> String {
-        let mut res = String::new();
-
-        for v in data {
-            if *v == 10 {
-                res.push('\n');
-            } else {
-                res.push(*v as u8 as char);
-            }
-        }
-
-        res
-    }
-}
\ No newline at end of file
+}
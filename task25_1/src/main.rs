@@ -1,23 +1,23 @@
 use crate::droid::*;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use common::intcode_loader::*;
 use common::log::*;
-use std::fs::File;
-use std::io::{prelude::*, BufReader};
+use std::env;
 
 mod droid;
 
 fn main() -> Result<()> {
     let log = Log::new(false);
-    let file = File::open("input.txt")?;
-    let reader = BufReader::new(file);
-    let prog_str = reader
-        .lines()
-        .nth(0)
-        .ok_or_else(|| anyhow!("ERROR: Cannot read program string."))??;
+    let prog_str = read_prog_text("input.txt")?;
 
     let mut droid = Droid::new(&prog_str, &log)?;
+    droid.set_show_memory(env::args().any(|arg| arg == "--memory"));
 
-    droid.interactive()?;
+    if env::args().any(|arg| arg == "--text") {
+        droid.text_mode()?;
+    } else {
+        droid.interactive()?;
+    }
 
     Ok(())
 }